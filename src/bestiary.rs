@@ -0,0 +1,153 @@
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::Deserialize;
+
+use crate::entities::{Behaviour, Character, Enemy};
+use crate::world::Point;
+
+/// Monster definitions bundled with the game.
+const MONSTERS: &str = include_str!("../data/monsters.ron");
+
+/// A kind of monster that can roam the dungeon.
+#[derive(Clone, Deserialize)]
+pub struct Monster {
+    pub name: String,
+    pub glyph: String,
+    pub health: i32,
+    pub attack: i32,
+    pub dodge: i32,
+    pub luck: i32,
+    /// Shallowest level the monster appears on, starting at 1
+    pub min_depth: usize,
+    /// Deepest level the monster appears on
+    pub max_depth: usize,
+    /// How rare the monster is, a monster of rarity 2 appears half as often
+    /// as one of rarity 1
+    pub rarity: u32,
+    pub behaviour: Vec<Behaviour>,
+}
+
+impl Monster {
+    /// Create one of these monsters standing at `location`.
+    pub fn create(&self, location: Point) -> Character {
+        let mut monster: Character = Enemy::new(
+            self.name.clone(),
+            self.health,
+            self.attack,
+            self.dodge,
+            self.luck,
+            location,
+            &self.glyph,
+        );
+        monster.set_behaviour(self.behaviour.clone());
+        monster
+    }
+
+    fn lives_at(&self, depth: usize) -> bool {
+        self.min_depth <= depth && depth <= self.max_depth
+    }
+}
+
+/// Every monster of the game.
+pub fn all() -> Vec<Monster> {
+    ron::de::from_str(MONSTERS).expect("Bundled monster data is broken")
+}
+
+/// Monsters that can spawn at a given depth, along with their chance to.
+pub struct SpawnTable {
+    monsters: Vec<Monster>,
+}
+
+impl SpawnTable {
+    /// Spawn table of the monsters living at `depth`. Below the deepest
+    /// monsters, the ones that reached the furthest down are used.
+    pub fn new(depth: usize) -> SpawnTable {
+        let bestiary = all();
+        let mut monsters: Vec<Monster> = bestiary
+            .iter()
+            .filter(|m| m.lives_at(depth))
+            .cloned()
+            .collect();
+        if monsters.is_empty() {
+            let deepest = bestiary.iter().map(|m| m.max_depth).max().unwrap_or(0);
+            monsters = bestiary
+                .into_iter()
+                .filter(|m| m.max_depth == deepest)
+                .collect();
+        }
+        SpawnTable { monsters }
+    }
+
+    pub fn monsters(&self) -> &[Monster] {
+        &self.monsters
+    }
+
+    /// Pick a monster at random, weighted by rarity.
+    pub fn pick<R: Rng>(&self, rng: &mut R) -> Option<&Monster> {
+        self.monsters
+            .choose_weighted(rng, |m| 1.0 / f64::from(m.rarity.max(1)))
+            .ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::Entity;
+    use crate::tiling::tile_to_str;
+    use crate::world::DungeonRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_bundled_monsters_load() {
+        let monsters = all();
+        assert!(monsters.len() > 5);
+        assert!(monsters.iter().all(|m| m.min_depth <= m.max_depth));
+    }
+
+    #[test]
+    fn test_spawn_table_follows_depth() {
+        let table = SpawnTable::new(1);
+        assert!(table.monsters().iter().any(|m| m.name == "rat"));
+        assert!(table.monsters().iter().all(|m| m.min_depth == 1));
+
+        let table = SpawnTable::new(5);
+        assert!(table.monsters().iter().all(|m| m.name != "rat"));
+        assert!(table.monsters().iter().any(|m| m.name == "troll"));
+    }
+
+    #[test]
+    fn test_spawn_table_falls_back_on_deepest_monsters() {
+        let table = SpawnTable::new(1000);
+        assert!(!table.monsters().is_empty());
+        assert!(table.monsters().iter().all(|m| m.max_depth == 99));
+    }
+
+    #[test]
+    fn test_rare_monsters_spawn_less_often() {
+        let table = SpawnTable::new(1);
+        let mut rng = DungeonRng::seed_from_u64(0);
+        let mut rats = 0;
+        let mut lichens = 0;
+        for _ in 0..1000 {
+            match table.pick(&mut rng).unwrap().name.as_str() {
+                "rat" => rats += 1,
+                "lichen" => lichens += 1,
+                _ => (),
+            }
+        }
+        assert!(rats > 2 * lichens);
+    }
+
+    #[test]
+    fn test_created_monster_has_its_glyph_and_quirks() {
+        let lichen = all().into_iter().find(|m| m.name == "lichen").unwrap();
+        let mut monster = lichen.create((3, 4));
+        monster.visibility(true);
+
+        assert_eq!(*monster.location(), (3, 4));
+        assert_eq!(tile_to_str(monster.tile()), "F");
+        assert!(monster.behaves(Behaviour::Stationary));
+        assert!(!monster.behaves(Behaviour::Cowardly));
+    }
+}
@@ -0,0 +1,121 @@
+use serde::Deserialize;
+
+use crate::entities::{Character, Growth, Player};
+use crate::items::Item;
+
+/// Class definitions bundled with the game.
+const CLASSES: &str = include_str!("../data/classes.ron");
+
+/// An item a class starts the game with.
+#[derive(Clone, Deserialize)]
+pub struct StartingItem {
+    pub item: Item,
+    /// Whether the item is worn from the start
+    pub worn: bool,
+}
+
+/// What a character starts with and how they grow, depending on their class.
+#[derive(Clone, Deserialize)]
+pub struct Class {
+    pub name: String,
+    pub description: String,
+    pub health: i32,
+    pub attack: i32,
+    pub dodge: i32,
+    pub luck: i32,
+    pub sight: usize,
+    pub growth: Growth,
+    pub kit: Vec<StartingItem>,
+}
+
+impl Class {
+    /// Create a level 1 character of this class named `name`, wearing and
+    /// carrying their starting kit.
+    pub fn create(&self, name: String) -> Character {
+        let mut character: Character = Player::new(
+            name,
+            self.name.clone(),
+            self.health,
+            self.attack,
+            self.dodge,
+            self.luck,
+        );
+        character.sight = self.sight;
+        character.set_growth(self.growth);
+
+        for start in &self.kit {
+            character.give(start.item.clone());
+            if start.worn {
+                let index = character.inventory().len() - 1;
+                if let Err(info) = character.equip(index) {
+                    log::warn!("Bad starting kit for {}: {}", self.name, info);
+                }
+            }
+        }
+        character
+    }
+
+    /// One line summary of the class, for the selection screen.
+    pub fn summary(&self) -> String {
+        format!(
+            "{} - hp: {} attack: {} dodge: {} luck: {} sight: {} - {}",
+            self.name,
+            self.health,
+            self.attack,
+            self.dodge,
+            self.luck,
+            self.sight,
+            self.description
+        )
+    }
+}
+
+/// Every class the player can pick from.
+pub fn all() -> Vec<Class> {
+    ron::de::from_str(CLASSES).expect("Bundled class data is broken")
+}
+
+/// Find a class by name, ignoring case.
+pub fn find(name: &str) -> Option<Class> {
+    all()
+        .into_iter()
+        .find(|class| class.name.eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::items::Slot;
+
+    #[test]
+    fn test_bundled_classes_load() {
+        let classes = all();
+        assert!(classes.len() >= 3);
+        assert!(classes.iter().all(|class| !class.kit.is_empty()));
+    }
+
+    #[test]
+    fn test_classes_are_found_by_name() {
+        assert_eq!(find("rogue").unwrap().name, "Rogue");
+        assert!(find("Bard").is_none());
+    }
+
+    #[test]
+    fn test_created_character_wears_their_kit() {
+        let warrior = find("Warrior").unwrap().create(String::from("Tester"));
+
+        assert_eq!(warrior.class, "Warrior");
+        assert!(warrior.equipment().get(Slot::Weapon).is_some());
+        assert!(warrior.equipment().get(Slot::Armor).is_some());
+        assert_eq!(warrior.inventory().len(), 2);
+    }
+
+    #[test]
+    fn test_classes_play_differently() {
+        let warrior = find("Warrior").unwrap().create(String::from("Tester"));
+        let mage = find("Mage").unwrap().create(String::from("Tester"));
+
+        assert!(mage.sight > warrior.sight);
+        assert!(mage.attack() < warrior.attack());
+    }
+}
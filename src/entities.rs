@@ -1,8 +1,13 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::cmp;
 
+use crate::items::{Equipment, Item, Slot, INVENTORY_CAPACITY};
 use crate::tiling::{Tile, TileType};
 use crate::world::{apply_movement, Movement, Point};
 
+/// How far characters see unless told otherwise.
+pub const DEFAULT_SIGHT: usize = 6;
+
 pub trait Entity {
     /// Get information about the entity
     fn info(&self) -> String;
@@ -24,14 +29,96 @@ pub trait Entity {
     fn clean(&mut self);
     fn visibility(&mut self, visible: bool);
     fn is_visible(&self) -> bool;
+    /// Get the entity as a character if it can fight
+    fn as_character(&self) -> Option<&Character> {
+        None
+    }
+    /// Get the entity as a character if it can fight
+    fn as_character_mut(&mut self) -> Option<&mut Character> {
+        None
+    }
+    /// Get the item if the entity is lying on the floor
+    fn as_item(&self) -> Option<&Item> {
+        None
+    }
+    /// Get a serializable copy of the entity
+    fn to_saved(&self) -> SavedEntity;
+}
+
+/// Serializable form of the entities a level can hold, as trait objects can't
+/// be (de)serialized directly.
+#[derive(Serialize, Deserialize)]
+pub enum SavedEntity {
+    Character(Box<Character>),
+    Item(FloorItem),
+}
+
+impl SavedEntity {
+    fn into_entity(self) -> Box<dyn Entity> {
+        match self {
+            SavedEntity::Character(character) => character,
+            SavedEntity::Item(item) => Box::new(item),
+        }
+    }
 }
 
-#[derive(Clone)]
+/// (De)serialize a list of entities, use with `#[serde(with = "entity_list")]`.
+pub mod entity_list {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        entities: &[Box<dyn Entity>],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(entities.iter().map(|e| e.to_saved()))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Box<dyn Entity>>, D::Error> {
+        let saved = Vec::<SavedEntity>::deserialize(deserializer)?;
+        Ok(saved.into_iter().map(SavedEntity::into_entity).collect())
+    }
+}
+
+/// Quirks of a monster's behaviour.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Behaviour {
+    /// Never moves
+    Stationary,
+    /// Wanders around half of the time, even when chasing
+    Erratic,
+    /// Runs away when badly hurt
+    Cowardly,
+}
+
+/// Stats gained with each experience level.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Growth {
+    pub health: i32,
+    pub attack: i32,
+    pub dodge: i32,
+}
+
+impl Default for Growth {
+    fn default() -> Self {
+        Growth {
+            health: 5,
+            attack: 1,
+            dodge: 1,
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Character {
     pub name: String,
     pub class: String,
     pub health: i32,
     pub level: i32,
+    pub sight: usize,
+    /// Turns left before the character stops fleeing
+    pub fear: u32,
     location: Point,
     previous_location: Point,
     dirty: bool,
@@ -40,9 +127,33 @@ pub struct Character {
     dodge: i32,
     luck: i32,
     xp: i32,
+    growth: Growth,
+    behaviour: Vec<Behaviour>,
+    tile: Tile,
+    equipment: Equipment,
+    inventory: Vec<Item>,
+}
+
+/// An item lying on the floor of a level, waiting to be picked up.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct FloorItem {
+    item: Item,
+    location: Point,
+    dirty: bool,
     tile: Tile,
 }
 
+impl FloorItem {
+    pub fn new(item: Item, location: Point) -> FloorItem {
+        FloorItem {
+            tile: Tile::from(TileType::Item(String::from(item.glyph()))),
+            item,
+            location,
+            dirty: true,
+        }
+    }
+}
+
 pub trait Enemy {
     fn new(
         class: String,
@@ -51,10 +162,17 @@ pub trait Enemy {
         dodge: i32,
         luck: i32,
         location: Point,
-        tile_str: &'static str,
+        tile_str: &str,
     ) -> Self;
 
     fn set_tile(&mut self, tile: Tile);
+    /// Experience earned by killing this enemy
+    fn xp_value(&self) -> i32;
+    fn set_behaviour(&mut self, behaviour: Vec<Behaviour>);
+    /// Whether the enemy has a given quirk
+    fn behaves(&self, behaviour: Behaviour) -> bool;
+    /// Whether the enemy is down to a third of its health or less
+    fn badly_hurt(&self) -> bool;
 }
 
 pub trait Player {
@@ -63,7 +181,32 @@ pub trait Player {
     fn heal(&mut self, heal_amount: i32);
     fn attack(&self) -> i32;
     fn dodge(&self) -> i32;
+    fn luck(&self) -> i32;
     fn stats(&self) -> String;
+    /// Change the stats gained with each level
+    fn set_growth(&mut self, growth: Growth);
+    /// Experience earned so far
+    fn xp(&self) -> i32;
+    /// Experience needed to reach the next level
+    fn next_level_xp(&self) -> i32;
+    /// Earn experience, going up as many levels as it allows. Returns a
+    /// message if the player levelled up.
+    fn gain_xp(&mut self, xp: i32) -> Option<String>;
+    /// Items worn by the player
+    fn equipment(&self) -> &Equipment;
+    /// Items carried but not worn by the player
+    fn inventory(&self) -> &[Item];
+    /// Put an item in the player's inventory
+    fn give(&mut self, item: Item);
+    /// Whether the inventory can't take any more items
+    fn inventory_full(&self) -> bool;
+    /// Take the item at `index` out of the inventory
+    fn take(&mut self, index: usize) -> Result<Item, String>;
+    /// Wear the inventory item at `index`, anything already worn in its slot
+    /// goes back to the inventory
+    fn equip(&mut self, index: usize) -> Result<String, String>;
+    /// Take off the item worn in `slot` and put it back in the inventory
+    fn unequip(&mut self, slot: Slot) -> Result<String, String>;
 }
 
 impl Entity for Character {
@@ -75,8 +218,8 @@ impl Entity for Character {
 
     fn info(&self) -> String {
         format!(
-            "{} \thp: {} attack: {} dodge: {} luck: {}",
-            self.class, self.health, self.attack, self.dodge, self.luck
+            "{} - hp: {}/{} attack: {} dodge: {} luck: {}",
+            self.name, self.health, self.max_health, self.attack, self.dodge, self.luck
         )
     }
 
@@ -122,6 +265,78 @@ impl Entity for Character {
     fn is_visible(&self) -> bool {
         self.tile.is_visible()
     }
+
+    fn as_character(&self) -> Option<&Character> {
+        Some(self)
+    }
+
+    fn as_character_mut(&mut self) -> Option<&mut Character> {
+        Some(self)
+    }
+
+    fn to_saved(&self) -> SavedEntity {
+        SavedEntity::Character(Box::new(self.clone()))
+    }
+}
+
+impl Entity for FloorItem {
+    fn place(&mut self, location: Point) {
+        self.location = location;
+        self.dirty = true;
+    }
+
+    fn info(&self) -> String {
+        self.item.to_string()
+    }
+
+    fn tile(&self) -> &Tile {
+        &self.tile
+    }
+
+    fn location(&self) -> &Point {
+        &self.location
+    }
+
+    fn previous_location(&self) -> &Point {
+        &self.location
+    }
+
+    fn move_to(&mut self, location: Point) {
+        self.place(location);
+    }
+
+    fn move_by(&mut self, movement: Movement) -> Result<(), String> {
+        self.location = apply_movement(self.location, movement)?;
+        self.dirty = true;
+        Ok(())
+    }
+
+    fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    fn clean(&mut self) {
+        self.dirty = false;
+    }
+
+    fn visibility(&mut self, visible: bool) {
+        if visible != self.is_visible() {
+            self.dirty = true;
+        }
+        self.tile.visibility(visible)
+    }
+
+    fn is_visible(&self) -> bool {
+        self.tile.is_visible()
+    }
+
+    fn as_item(&self) -> Option<&Item> {
+        Some(&self.item)
+    }
+
+    fn to_saved(&self) -> SavedEntity {
+        SavedEntity::Item(self.clone())
+    }
 }
 
 impl Enemy for Character {
@@ -132,7 +347,7 @@ impl Enemy for Character {
         dodge: i32,
         luck: i32,
         location: Point,
-        tile_str: &'static str,
+        tile_str: &str,
     ) -> Character {
         Character {
             name: class.clone(),
@@ -142,18 +357,40 @@ impl Enemy for Character {
             attack,
             dodge,
             luck,
-            level: 0,
+            level: 1,
             xp: 0,
+            growth: Growth::default(),
+            behaviour: vec![],
+            sight: DEFAULT_SIGHT,
+            fear: 0,
             location,
             previous_location: location,
-            tile: Tile::from(TileType::Character(tile_str)),
+            tile: Tile::from(TileType::Character(String::from(tile_str))),
             dirty: false,
+            equipment: Equipment::default(),
+            inventory: vec![],
         }
     }
 
     fn set_tile(&mut self, tile: Tile) {
         self.tile = tile
     }
+
+    fn xp_value(&self) -> i32 {
+        self.max_health + self.attack + self.dodge / 4
+    }
+
+    fn set_behaviour(&mut self, behaviour: Vec<Behaviour>) {
+        self.behaviour = behaviour;
+    }
+
+    fn behaves(&self, behaviour: Behaviour) -> bool {
+        self.behaviour.contains(&behaviour)
+    }
+
+    fn badly_hurt(&self) -> bool {
+        self.health * 3 <= self.max_health
+    }
 }
 
 impl Player for Character {
@@ -174,7 +411,11 @@ impl Player for Character {
             dodge,
             luck,
             xp: 0,
-            level: 0,
+            growth: Growth::default(),
+            behaviour: vec![],
+            level: 1,
+            sight: DEFAULT_SIGHT,
+            fear: 0,
             location: (0, 0),
             previous_location: (0, 0),
             tile: Tile::new(
@@ -185,40 +426,205 @@ impl Player for Character {
 		false
             ),
             dirty: false,
+            equipment: Equipment::default(),
+            inventory: vec![],
         }
     }
 
     fn damage(&mut self, damage_amount: i32) {
         self.health = cmp::max(0, self.health - damage_amount);
-        self.xp += 2;
     }
 
     fn heal(&mut self, heal_amount: i32) {
-        if (self.health) <= self.max_health {
-            self.health = cmp::min(self.health + heal_amount, self.max_health);
-            self.xp += 1;
-        }
+        self.health = cmp::min(self.health + heal_amount, self.max_health);
     }
 
     fn attack(&self) -> i32 {
-        self.xp + self.attack + self.luck / 2
+        self.attack + self.equipment.attack() + self.luck() / 2
     }
 
     fn dodge(&self) -> i32 {
-        self.xp + self.dodge + self.luck / 2
+        self.dodge + self.equipment.dodge() + self.luck() / 2
+    }
+
+    fn luck(&self) -> i32 {
+        self.luck + self.equipment.luck()
     }
 
     fn stats(&self) -> String {
         format!(
-            "{}({}) - hp: {}/{} attack: {} dodge: {} luck: {} experience: {}",
+            "{}({}) - hp: {}/{} attack: {} dodge: {} luck: {} level: {} xp: {}/{}",
             self.name,
             self.class,
             self.health,
             self.max_health,
-            self.attack,
-            self.dodge,
-            self.luck,
-            self.xp
+            self.attack + self.equipment.attack(),
+            self.dodge + self.equipment.dodge(),
+            self.luck(),
+            self.level,
+            self.xp,
+            self.next_level_xp()
         )
     }
+
+    fn set_growth(&mut self, growth: Growth) {
+        self.growth = growth;
+    }
+
+    fn xp(&self) -> i32 {
+        self.xp
+    }
+
+    fn next_level_xp(&self) -> i32 {
+        10 * self.level * (self.level + 1) / 2
+    }
+
+    fn gain_xp(&mut self, xp: i32) -> Option<String> {
+        let level = self.level;
+        self.xp += xp;
+        while self.xp >= self.next_level_xp() {
+            self.level += 1;
+            self.max_health += self.growth.health;
+            self.health += self.growth.health;
+            self.attack += self.growth.attack;
+            self.dodge += self.growth.dodge;
+        }
+
+        if self.level > level {
+            Some(format!("Welcome to level {}!", self.level))
+        } else {
+            None
+        }
+    }
+
+    fn equipment(&self) -> &Equipment {
+        &self.equipment
+    }
+
+    fn inventory(&self) -> &[Item] {
+        &self.inventory
+    }
+
+    fn give(&mut self, item: Item) {
+        self.inventory.push(item);
+    }
+
+    fn inventory_full(&self) -> bool {
+        self.inventory.len() >= INVENTORY_CAPACITY
+    }
+
+    fn take(&mut self, index: usize) -> Result<Item, String> {
+        if index >= self.inventory.len() {
+            return Err(String::from("No such item!"));
+        }
+        Ok(self.inventory.remove(index))
+    }
+
+    fn equip(&mut self, index: usize) -> Result<String, String> {
+        if index >= self.inventory.len() {
+            return Err(String::from("No such item!"));
+        }
+        if self.inventory[index].slot().is_none() {
+            return Err(format!(
+                "You can't wear the {}.",
+                self.inventory[index].name
+            ));
+        }
+
+        let item = self.inventory.remove(index);
+        let message = format!("You are now wearing the {}.", item);
+        if let Ok(Some(previous)) = self.equipment.put_on(item) {
+            self.inventory.push(previous);
+        }
+        Ok(message)
+    }
+
+    fn unequip(&mut self, slot: Slot) -> Result<String, String> {
+        if self.equipment.get(slot).is_none() {
+            return Err(format!("You aren't wearing any {}.", slot));
+        }
+        if self.inventory_full() {
+            return Err(String::from("Your pack is full."));
+        }
+        let item = self.equipment.take_off(slot).unwrap();
+        let message = format!("You take off the {}.", item.name);
+        self.inventory.push(item);
+        Ok(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::items::Effect;
+
+    fn new_player() -> Character {
+        Player::new(
+            String::from("Tester"),
+            String::from("Warrior"),
+            30,
+            10,
+            10,
+            0,
+        )
+    }
+
+    #[test]
+    fn test_taking_damage_and_healing_give_no_xp() {
+        let mut player = new_player();
+        player.damage(10);
+        player.heal(5);
+
+        assert_eq!(player.health, 25);
+        assert_eq!(player.xp, 0);
+        assert_eq!(player.attack(), 10);
+    }
+
+    #[test]
+    fn test_healing_stops_at_max_health() {
+        let mut player = new_player();
+        player.damage(3);
+        player.heal(10);
+        assert_eq!(player.health, 30);
+    }
+
+    #[test]
+    fn test_xp_raises_level_and_stats() {
+        let mut player = new_player();
+        assert_eq!(player.level, 1);
+        assert_eq!(player.gain_xp(9), None);
+        assert_eq!(player.level, 1);
+
+        assert_eq!(player.gain_xp(1), Some(String::from("Welcome to level 2!")));
+        assert_eq!(player.max_health, 35);
+        assert_eq!(player.health, 35);
+        assert_eq!(player.attack(), 11);
+        assert_eq!(player.dodge(), 11);
+
+        // Enough experience for several levels at once.
+        player.gain_xp(100);
+        assert_eq!(player.level, 5);
+        assert_eq!(player.next_level_xp(), 150);
+    }
+
+    #[test]
+    fn test_unequipping_an_empty_slot_says_so_even_with_a_full_pack() {
+        let mut player = new_player();
+        for _ in 0..INVENTORY_CAPACITY {
+            player.give(Item::potion("healing potion", Effect::Heal(3)));
+        }
+        assert_eq!(
+            player.unequip(Slot::Armor),
+            Err(String::from("You aren't wearing any armor."))
+        );
+
+        player
+            .equipment
+            .put_on(Item::equipment("mail", Slot::Armor, 0, 2, 0))
+            .unwrap();
+        assert_eq!(
+            player.unequip(Slot::Armor),
+            Err(String::from("Your pack is full."))
+        );
+    }
 }
@@ -1,10 +1,35 @@
+use crate::items::Slot;
+use crate::state::Destination;
 use crate::world::Movement;
 
 #[derive(Copy, Clone, Debug)]
 pub enum ViewportEvent {
     Quit,
+    /// Start over with a new game
+    NewGame,
+    SaveQuit,
     Help,
     MovePlayer(Movement),
     DownStairs,
     UpStairs,
+    /// Wear the inventory item at the given index
+    Equip(usize),
+    /// Take off the item worn in the given slot
+    Unequip(Slot),
+    /// Pick up the items under the player
+    PickUp,
+    /// Drink or read the inventory item at the given index
+    Use(usize),
+    /// Drop the inventory item at the given index
+    Drop(usize),
+    /// Show what the player carries and wears
+    Inventory,
+    /// Show every message so far
+    History,
+    /// Walk around until something interesting comes up
+    Explore,
+    /// Walk to the given destination
+    Travel(Destination),
+    /// Look at the map
+    Look,
 }
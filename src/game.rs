@@ -0,0 +1,158 @@
+use std::path::PathBuf;
+
+use crate::events::ViewportEvent;
+use crate::morgue;
+use crate::save;
+use crate::scores::{self, Score};
+use crate::state::State;
+use crate::viewport::ViewPort;
+
+/// How a game ended.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Ending {
+    /// The player left, or died and didn't want to play again
+    Quit,
+    /// The game was saved to be resumed later
+    Saved,
+    /// The player died and wants to play again
+    NewGame,
+}
+
+/// Where a game keeps what outlives it.
+pub struct GameFiles {
+    /// Where the game is saved on save and quit
+    pub save: PathBuf,
+    /// High score table, scores aren't kept if not given
+    pub scores: Option<PathBuf>,
+    /// Directory of the morgue files, none are written if not given
+    pub morgue: Option<PathBuf>,
+}
+
+/// Log the outcome of a player action. If it succeeded, the enemies act,
+/// which ends the turn. Returns whether the turn went by without anything
+/// worth telling the player.
+fn take_turn(state: &mut State, outcome: Result<Option<String>, String>) -> bool {
+    match outcome {
+        Ok(info) => {
+            let messages: Vec<String> = info.into_iter().chain(state.enemies_turn()).collect();
+            let quiet = messages.is_empty();
+            for message in messages {
+                state.log(message);
+            }
+            quiet
+        }
+        Err(info) => {
+            state.log(info);
+            false
+        }
+    }
+}
+
+/// Repeat `step` one turn at a time, showing each, until it fails, something
+/// worth telling happens, the player dies or they press a key.
+fn walk<F>(state: &mut State, window: &mut dyn ViewPort, step: F)
+where
+    F: Fn(&mut State) -> Result<Option<String>, String>,
+{
+    loop {
+        let outcome = step(state);
+        if !take_turn(state, outcome) || state.is_dead() {
+            break;
+        }
+        window.render_state(state);
+        if window.interrupted() {
+            break;
+        }
+    }
+}
+
+/// Record the dead player's score and morgue file, then ask them whether to
+/// play again.
+fn game_over(state: &mut State, window: &mut dyn ViewPort, files: &GameFiles) -> Ending {
+    window.render_state(state);
+    let (table, rank) = match &files.scores {
+        Some(path) => match scores::record(path, Score::from_state(state)) {
+            Ok(recorded) => recorded,
+            Err(info) => {
+                state.log(info);
+                (vec![], None)
+            }
+        },
+        None => (vec![], None),
+    };
+    if let Some(dir) = &files.morgue {
+        match morgue::write(dir, state) {
+            Ok(path) => state.log(format!("Morgue file: {}", path.display())),
+            Err(info) => state.log(info),
+        }
+    }
+    match window.game_over(state, &table, rank) {
+        ViewportEvent::NewGame => Ending::NewGame,
+        _ => Ending::Quit,
+    }
+}
+
+/// Main loop, dispatches events and calls rendering routines until the player
+/// quits or dies. Don't add any game logic here.
+pub fn run(state: &mut State, window: &mut dyn ViewPort, files: &GameFiles) -> Ending {
+    loop {
+        window.render_state(state);
+
+        if let Some(event) = window.wait_input() {
+            let outcome = match event {
+                ViewportEvent::Quit => return Ending::Quit,
+                ViewportEvent::SaveQuit => match save::save_game(state, &files.save) {
+                    Ok(()) => return Ending::Saved,
+                    Err(info) => Err(info),
+                },
+                ViewportEvent::MovePlayer(direction) => state.move_player(direction),
+                ViewportEvent::DownStairs => state.down_stairs().map(|_| None),
+                ViewportEvent::UpStairs => state.up_stairs().map(|_| None),
+                ViewportEvent::Equip(index) => state.equip(index),
+                ViewportEvent::Unequip(slot) => state.unequip(slot),
+                ViewportEvent::PickUp => state.pick_up(),
+                ViewportEvent::Use(index) => state.use_item(index),
+                ViewportEvent::Drop(index) => state.drop(index),
+                ViewportEvent::Explore => {
+                    walk(state, window, State::explore);
+                    if state.is_dead() {
+                        return game_over(state, window, files);
+                    }
+                    continue;
+                }
+                ViewportEvent::Travel(destination) => {
+                    walk(state, window, |state| state.travel(destination));
+                    if state.is_dead() {
+                        return game_over(state, window, files);
+                    }
+                    continue;
+                }
+                // Looking around, at the inventory, messages or help doesn't
+                // take a turn.
+                ViewportEvent::Look => {
+                    window.look(state);
+                    continue;
+                }
+                ViewportEvent::Inventory => {
+                    window.show_inventory(state);
+                    continue;
+                }
+                ViewportEvent::History => {
+                    window.show_history(state);
+                    continue;
+                }
+                ViewportEvent::Help => {
+                    window.show_help();
+                    continue;
+                }
+                _ => continue,
+            };
+
+            take_turn(state, outcome);
+
+            if state.is_dead() {
+                return game_over(state, window, files);
+            }
+        }
+    }
+}
@@ -0,0 +1,377 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// How many items a character can carry besides what they wear.
+pub const INVENTORY_CAPACITY: usize = 20;
+
+/// Equipment that can be found lying in the dungeon, as name, slot, attack,
+/// dodge and luck.
+const EQUIPMENT: [(&str, Slot, i32, i32, i32); 10] = [
+    ("dagger", Slot::Weapon, 1, 0, 0),
+    ("short sword", Slot::Weapon, 2, 0, 0),
+    ("mace", Slot::Weapon, 3, -1, 0),
+    ("long sword", Slot::Weapon, 4, 0, 0),
+    ("leather armor", Slot::Armor, 0, 2, 0),
+    ("chain mail", Slot::Armor, 0, 4, -1),
+    ("plate mail", Slot::Armor, -1, 6, -2),
+    ("rabbit's foot", Slot::Accessory, 0, 0, 4),
+    ("ring of dodging", Slot::Accessory, 0, 3, 0),
+    ("amulet of might", Slot::Accessory, 2, 0, 1),
+];
+
+/// Potions and scrolls that can be found lying in the dungeon.
+const CONSUMABLES: [(&str, Effect, bool); 4] = [
+    ("healing potion", Effect::Heal(10), true),
+    ("scroll of teleportation", Effect::Teleport, false),
+    ("scroll of magic mapping", Effect::MagicMapping, false),
+    ("scroll of fear", Effect::Fear, false),
+];
+
+/// Letter used to pick the item at `index` in a list.
+pub fn letter(index: usize) -> char {
+    (b'a' + index as u8) as char
+}
+
+/// Index of the item picked with `letter` in a list.
+pub fn index_of(letter: char) -> Option<usize> {
+    if letter.is_ascii_lowercase() {
+        Some(letter as usize - 'a' as usize)
+    } else {
+        None
+    }
+}
+
+/// Where a piece of equipment is worn.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Slot {
+    Weapon,
+    Armor,
+    Accessory,
+}
+
+impl fmt::Display for Slot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Slot::Weapon => "weapon",
+                Slot::Armor => "armor",
+                Slot::Accessory => "accessory",
+            }
+        )
+    }
+}
+
+/// What happens when a potion or scroll is used.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Effect {
+    /// Restore some health
+    Heal(i32),
+    /// Move the user to a random spot of the level
+    Teleport,
+    /// Reveal the whole level
+    MagicMapping,
+    /// Make the monsters in sight flee
+    Fear,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ItemKind {
+    /// Worn in `slot`, adds its bonuses to the wearer's stats.
+    Equipment {
+        slot: Slot,
+        attack: i32,
+        dodge: i32,
+        luck: i32,
+    },
+    /// Drunk once for its effect.
+    Potion(Effect),
+    /// Read once for its effect.
+    Scroll(Effect),
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Item {
+    pub name: String,
+    pub kind: ItemKind,
+}
+
+impl Item {
+    pub fn equipment(name: &str, slot: Slot, attack: i32, dodge: i32, luck: i32) -> Item {
+        Item {
+            name: String::from(name),
+            kind: ItemKind::Equipment {
+                slot,
+                attack,
+                dodge,
+                luck,
+            },
+        }
+    }
+
+    pub fn potion(name: &str, effect: Effect) -> Item {
+        Item {
+            name: String::from(name),
+            kind: ItemKind::Potion(effect),
+        }
+    }
+
+    pub fn scroll(name: &str, effect: Effect) -> Item {
+        Item {
+            name: String::from(name),
+            kind: ItemKind::Scroll(effect),
+        }
+    }
+
+    /// Pick one of the items that can be found in the dungeon.
+    pub fn random<R: Rng>(rng: &mut R) -> Item {
+        let index = rng.gen_range(0, EQUIPMENT.len() + CONSUMABLES.len());
+        if index < EQUIPMENT.len() {
+            let (name, slot, attack, dodge, luck) = EQUIPMENT[index];
+            return Item::equipment(name, slot, attack, dodge, luck);
+        }
+
+        match CONSUMABLES[index - EQUIPMENT.len()] {
+            (name, effect, true) => Item::potion(name, effect),
+            (name, effect, false) => Item::scroll(name, effect),
+        }
+    }
+
+    /// Symbol of the item when it lies on the floor.
+    pub fn glyph(&self) -> &'static str {
+        match self.kind {
+            ItemKind::Equipment { slot, .. } => match slot {
+                Slot::Weapon => ")",
+                Slot::Armor => "[",
+                Slot::Accessory => "=",
+            },
+            ItemKind::Potion(_) => "!",
+            ItemKind::Scroll(_) => "?",
+        }
+    }
+
+    /// Slot the item is worn in, if it can be worn.
+    pub fn slot(&self) -> Option<Slot> {
+        match self.kind {
+            ItemKind::Equipment { slot, .. } => Some(slot),
+            _ => None,
+        }
+    }
+
+    /// Effect of the item if it can be used up.
+    pub fn effect(&self) -> Option<Effect> {
+        match self.kind {
+            ItemKind::Potion(effect) | ItemKind::Scroll(effect) => Some(effect),
+            _ => None,
+        }
+    }
+
+    /// How the item is used up, to describe it to the player.
+    pub fn verb(&self) -> &'static str {
+        match self.kind {
+            ItemKind::Potion(_) => "drink",
+            ItemKind::Scroll(_) => "read",
+            ItemKind::Equipment { .. } => "use",
+        }
+    }
+
+    fn attack(&self) -> i32 {
+        match self.kind {
+            ItemKind::Equipment { attack, .. } => attack,
+            _ => 0,
+        }
+    }
+
+    fn dodge(&self) -> i32 {
+        match self.kind {
+            ItemKind::Equipment { dodge, .. } => dodge,
+            _ => 0,
+        }
+    }
+
+    fn luck(&self) -> i32 {
+        match self.kind {
+            ItemKind::Equipment { luck, .. } => luck,
+            _ => 0,
+        }
+    }
+}
+
+impl fmt::Display for Item {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        let bonuses: Vec<String> = [
+            ("attack", self.attack()),
+            ("dodge", self.dodge()),
+            ("luck", self.luck()),
+        ]
+        .iter()
+        .filter(|(_, value)| *value != 0)
+        .map(|(stat, value)| format!("{:+} {}", value, stat))
+        .collect();
+        if !bonuses.is_empty() {
+            write!(f, " ({})", bonuses.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Items worn by a character, one per slot.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Equipment {
+    weapon: Option<Item>,
+    armor: Option<Item>,
+    accessory: Option<Item>,
+}
+
+impl Equipment {
+    fn slot_mut(&mut self, slot: Slot) -> &mut Option<Item> {
+        match slot {
+            Slot::Weapon => &mut self.weapon,
+            Slot::Armor => &mut self.armor,
+            Slot::Accessory => &mut self.accessory,
+        }
+    }
+
+    pub fn get(&self, slot: Slot) -> Option<&Item> {
+        match slot {
+            Slot::Weapon => self.weapon.as_ref(),
+            Slot::Armor => self.armor.as_ref(),
+            Slot::Accessory => self.accessory.as_ref(),
+        }
+    }
+
+    /// Wear `item`, returning whatever was worn in its slot before. Items that
+    /// can't be worn are handed back as an error.
+    pub fn put_on(&mut self, item: Item) -> Result<Option<Item>, Item> {
+        match item.slot() {
+            Some(slot) => Ok(self.slot_mut(slot).replace(item)),
+            None => Err(item),
+        }
+    }
+
+    pub fn take_off(&mut self, slot: Slot) -> Option<Item> {
+        self.slot_mut(slot).take()
+    }
+
+    pub fn items(&self) -> impl Iterator<Item = &Item> {
+        self.weapon
+            .iter()
+            .chain(self.armor.iter())
+            .chain(self.accessory.iter())
+    }
+
+    pub fn attack(&self) -> i32 {
+        self.items().map(Item::attack).sum()
+    }
+
+    pub fn dodge(&self) -> i32 {
+        self.items().map(Item::dodge).sum()
+    }
+
+    pub fn luck(&self) -> i32 {
+        self.items().map(Item::luck).sum()
+    }
+}
+
+/// Lines of the inventory screen, what is carried then what is worn.
+pub fn inventory_lines(inventory: &[Item], equipment: &Equipment) -> Vec<String> {
+    let mut lines = vec![format!(
+        "Inventory ({}/{})",
+        inventory.len(),
+        INVENTORY_CAPACITY
+    )];
+    if inventory.is_empty() {
+        lines.push(String::from("  You aren't carrying anything."));
+    }
+    for (i, item) in inventory.iter().enumerate() {
+        lines.push(format!("  {}) {}", letter(i), item));
+    }
+
+    lines.push(String::new());
+    lines.push(String::from("Equipment"));
+    for slot in [Slot::Weapon, Slot::Armor, Slot::Accessory].iter() {
+        let worn = match equipment.get(*slot) {
+            Some(item) => item.to_string(),
+            None => String::from("-"),
+        };
+        lines.push(format!("  {}: {}", slot, worn));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_letters_map_to_indexes() {
+        assert_eq!(letter(0), 'a');
+        assert_eq!(letter(2), 'c');
+        assert_eq!(index_of('a'), Some(0));
+        assert_eq!(index_of('z'), Some(25));
+        assert_eq!(index_of('A'), None);
+    }
+
+    #[test]
+    fn test_equipment_adds_up_bonuses() {
+        let mut equipment = Equipment::default();
+        equipment
+            .put_on(Item::equipment("sword", Slot::Weapon, 3, 0, 0))
+            .unwrap();
+        equipment
+            .put_on(Item::equipment("ring", Slot::Accessory, 1, 2, 4))
+            .unwrap();
+
+        assert_eq!(equipment.attack(), 4);
+        assert_eq!(equipment.dodge(), 2);
+        assert_eq!(equipment.luck(), 4);
+    }
+
+    #[test]
+    fn test_putting_on_an_item_returns_the_previous_one() {
+        let mut equipment = Equipment::default();
+        let dagger = Item::equipment("dagger", Slot::Weapon, 1, 0, 0);
+        let sword = Item::equipment("sword", Slot::Weapon, 3, 0, 0);
+
+        assert_eq!(equipment.put_on(dagger.clone()), Ok(None));
+        assert_eq!(equipment.put_on(sword.clone()), Ok(Some(dagger)));
+        assert_eq!(equipment.get(Slot::Weapon), Some(&sword));
+        assert_eq!(equipment.take_off(Slot::Weapon), Some(sword));
+        assert_eq!(equipment.get(Slot::Weapon), None);
+    }
+
+    #[test]
+    fn test_items_have_a_glyph_per_kind() {
+        assert_eq!(
+            Item::equipment("dagger", Slot::Weapon, 1, 0, 0).glyph(),
+            ")"
+        );
+        assert_eq!(Item::equipment("mail", Slot::Armor, 0, 4, 0).glyph(), "[");
+        assert_eq!(
+            Item::equipment("ring", Slot::Accessory, 0, 0, 1).glyph(),
+            "="
+        );
+        assert_eq!(Item::potion("potion", Effect::Heal(1)).glyph(), "!");
+        assert_eq!(Item::scroll("scroll", Effect::Fear).glyph(), "?");
+    }
+
+    #[test]
+    fn test_only_potions_and_scrolls_have_effects() {
+        let potion = Item::potion("healing potion", Effect::Heal(10));
+        let sword = Item::equipment("sword", Slot::Weapon, 3, 0, 0);
+
+        assert_eq!(potion.effect(), Some(Effect::Heal(10)));
+        assert_eq!(potion.slot(), None);
+        assert_eq!(potion.to_string(), "healing potion");
+        assert_eq!(sword.effect(), None);
+    }
+
+    #[test]
+    fn test_item_description_lists_bonuses() {
+        let ring = Item::equipment("ring", Slot::Accessory, 0, 2, -1);
+        assert_eq!(ring.to_string(), "ring (+2 dodge, -1 luck)");
+    }
+}
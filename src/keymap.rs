@@ -0,0 +1,510 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::world::{Movement, DOWN, DOWN_LEFT, DOWN_RIGHT, LEFT, RIGHT, UP, UP_LEFT, UP_RIGHT};
+
+/// Name of the key bindings file in the player's config directory.
+const KEYS_FILE: &str = "keys.ron";
+
+/// A key the player can press, independent of the terminal library.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Key {
+    Char(char),
+    Ctrl(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Enter,
+    Esc,
+    Tab,
+    BackTab,
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Key::Char(c) => write!(f, "{}", c),
+            Key::Ctrl(c) => write!(f, "Ctrl-{}", c),
+            Key::Up => write!(f, "Up"),
+            Key::Down => write!(f, "Down"),
+            Key::Left => write!(f, "Left"),
+            Key::Right => write!(f, "Right"),
+            Key::Home => write!(f, "Home"),
+            Key::End => write!(f, "End"),
+            Key::PageUp => write!(f, "PageUp"),
+            Key::PageDown => write!(f, "PageDown"),
+            Key::Enter => write!(f, "Enter"),
+            Key::Esc => write!(f, "Esc"),
+            Key::Tab => write!(f, "Tab"),
+            Key::BackTab => write!(f, "BackTab"),
+        }
+    }
+}
+
+impl FromStr for Key {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Key, String> {
+        let mut chars = name.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => return Ok(Key::Char(c)),
+            (None, _) => return Err(String::from("A key can't be empty.")),
+            _ => (),
+        }
+        if let Some(c) = name.strip_prefix("Ctrl-") {
+            let mut chars = c.chars();
+            if let (Some(c), None) = (chars.next(), chars.next()) {
+                return Ok(Key::Ctrl(c));
+            }
+        }
+        match name {
+            "Up" => Ok(Key::Up),
+            "Down" => Ok(Key::Down),
+            "Left" => Ok(Key::Left),
+            "Right" => Ok(Key::Right),
+            "Home" => Ok(Key::Home),
+            "End" => Ok(Key::End),
+            "PageUp" => Ok(Key::PageUp),
+            "PageDown" => Ok(Key::PageDown),
+            "Enter" => Ok(Key::Enter),
+            "Esc" => Ok(Key::Esc),
+            "Tab" => Ok(Key::Tab),
+            "BackTab" => Ok(Key::BackTab),
+            _ => Err(format!("Unknown key {}.", name)),
+        }
+    }
+}
+
+/// Where keys are read. A key can do something different on each screen.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Screen {
+    /// Playing on the level
+    Level,
+    /// Picking where to travel
+    Travel,
+    /// Looking around
+    Look,
+    /// Picking what to take off
+    TakeOff,
+    /// Reading the message history
+    History,
+    /// Dead, picking whether to play again
+    GameOver,
+    /// Picking the class of a new character
+    Classes,
+}
+
+/// Something the player can ask for by pressing a key.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Deserialize)]
+pub enum Command {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    MoveUpLeft,
+    MoveUpRight,
+    MoveDownLeft,
+    MoveDownRight,
+    DownStairs,
+    UpStairs,
+    Explore,
+    Travel,
+    Look,
+    PickUp,
+    Wear,
+    TakeOff,
+    Use,
+    Drop,
+    Inventory,
+    History,
+    Help,
+    SaveQuit,
+    Quit,
+    Confirm,
+    Cancel,
+    NextTarget,
+    PreviousTarget,
+    ScrollUp,
+    ScrollDown,
+    ScrollPageUp,
+    ScrollPageDown,
+    TakeOffWeapon,
+    TakeOffArmor,
+    TakeOffAccessory,
+    NewGame,
+}
+
+impl Command {
+    /// Every command, in the order the help screen lists them.
+    pub const ALL: [Command; 35] = [
+        Command::MoveUp,
+        Command::MoveDown,
+        Command::MoveLeft,
+        Command::MoveRight,
+        Command::MoveUpLeft,
+        Command::MoveUpRight,
+        Command::MoveDownLeft,
+        Command::MoveDownRight,
+        Command::DownStairs,
+        Command::UpStairs,
+        Command::Explore,
+        Command::Travel,
+        Command::Look,
+        Command::PickUp,
+        Command::Wear,
+        Command::TakeOff,
+        Command::Use,
+        Command::Drop,
+        Command::Inventory,
+        Command::History,
+        Command::Help,
+        Command::SaveQuit,
+        Command::Quit,
+        Command::Confirm,
+        Command::Cancel,
+        Command::NextTarget,
+        Command::PreviousTarget,
+        Command::ScrollUp,
+        Command::ScrollDown,
+        Command::ScrollPageUp,
+        Command::ScrollPageDown,
+        Command::TakeOffWeapon,
+        Command::TakeOffArmor,
+        Command::TakeOffAccessory,
+        Command::NewGame,
+    ];
+
+    /// Screens the command can be used on.
+    pub fn screens(self) -> &'static [Screen] {
+        match self {
+            Command::MoveUp
+            | Command::MoveDown
+            | Command::MoveLeft
+            | Command::MoveRight
+            | Command::MoveUpLeft
+            | Command::MoveUpRight
+            | Command::MoveDownLeft
+            | Command::MoveDownRight => &[Screen::Level, Screen::Travel, Screen::Look],
+            Command::DownStairs | Command::UpStairs => &[Screen::Level, Screen::Travel],
+            Command::Quit => &[Screen::Level, Screen::History, Screen::GameOver],
+            Command::Confirm => &[Screen::Travel],
+            Command::Cancel => &[
+                Screen::Travel,
+                Screen::Look,
+                Screen::History,
+                Screen::GameOver,
+                Screen::Classes,
+            ],
+            Command::NextTarget | Command::PreviousTarget => &[Screen::Look],
+            Command::ScrollUp
+            | Command::ScrollDown
+            | Command::ScrollPageUp
+            | Command::ScrollPageDown => &[Screen::History],
+            Command::TakeOffWeapon | Command::TakeOffArmor | Command::TakeOffAccessory => {
+                &[Screen::TakeOff]
+            }
+            Command::NewGame => &[Screen::GameOver],
+            _ => &[Screen::Level],
+        }
+    }
+
+    /// Direction of the movement commands.
+    pub fn movement(self) -> Option<Movement> {
+        match self {
+            Command::MoveUp => Some(UP),
+            Command::MoveDown => Some(DOWN),
+            Command::MoveLeft => Some(LEFT),
+            Command::MoveRight => Some(RIGHT),
+            Command::MoveUpLeft => Some(UP_LEFT),
+            Command::MoveUpRight => Some(UP_RIGHT),
+            Command::MoveDownLeft => Some(DOWN_LEFT),
+            Command::MoveDownRight => Some(DOWN_RIGHT),
+            _ => None,
+        }
+    }
+
+    /// What the command does, for the help screen.
+    pub fn description(self) -> &'static str {
+        match self {
+            Command::MoveUp => "move or attack up",
+            Command::MoveDown => "move or attack down",
+            Command::MoveLeft => "move or attack left",
+            Command::MoveRight => "move or attack right",
+            Command::MoveUpLeft => "move or attack up left",
+            Command::MoveUpRight => "move or attack up right",
+            Command::MoveDownLeft => "move or attack down left",
+            Command::MoveDownRight => "move or attack down right",
+            Command::DownStairs => "go down the stairs",
+            Command::UpStairs => "go up the stairs",
+            Command::Explore => "explore",
+            Command::Travel => "travel",
+            Command::Look => "look around",
+            Command::PickUp => "pick up",
+            Command::Wear => "wear",
+            Command::TakeOff => "take off",
+            Command::Use => "quaff or read",
+            Command::Drop => "drop",
+            Command::Inventory => "inventory",
+            Command::History => "message history",
+            Command::Help => "this help",
+            Command::SaveQuit => "save and quit",
+            Command::Quit => "quit without saving",
+            Command::Confirm => "travel to the cursor",
+            Command::Cancel => "cancel",
+            Command::NextTarget => "next monster",
+            Command::PreviousTarget => "previous monster",
+            Command::ScrollUp => "scroll up",
+            Command::ScrollDown => "scroll down",
+            Command::ScrollPageUp => "scroll a page up",
+            Command::ScrollPageDown => "scroll a page down",
+            Command::TakeOffWeapon => "take off the weapon",
+            Command::TakeOffArmor => "take off the armor",
+            Command::TakeOffAccessory => "take off the accessory",
+            Command::NewGame => "play again",
+        }
+    }
+}
+
+/// Default key bindings bundled with the game.
+const KEYS: &str = include_str!("../data/keys.ron");
+
+/// Which key triggers which command.
+pub struct Keymap {
+    bindings: Vec<(Key, Command)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        let config = parse(KEYS).expect("Bundled key bindings are broken");
+        Keymap {
+            bindings: Command::ALL
+                .iter()
+                .flat_map(|command| config[command].iter().map(move |key| (*key, *command)))
+                .collect(),
+        }
+    }
+}
+
+/// Read key bindings written like `data/keys.ron`.
+fn parse(config: &str) -> Result<HashMap<Command, Vec<Key>>, String> {
+    let names: HashMap<Command, Vec<String>> =
+        ron::de::from_str(config).map_err(|e| e.to_string())?;
+    names
+        .into_iter()
+        .map(|(command, keys)| {
+            let keys = keys
+                .iter()
+                .map(|key| key.parse())
+                .collect::<Result<Vec<Key>, String>>()?;
+            Ok((command, keys))
+        })
+        .collect()
+}
+
+/// Default place of the player's key bindings, in their config directory.
+pub fn keymap_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("roguerust").join(KEYS_FILE))
+}
+
+impl Keymap {
+    /// Default bindings, with the commands listed in `config` bound to its
+    /// keys instead. Fails if a key ends up bound to several commands, or a
+    /// command to none.
+    pub fn from_config(config: &str) -> Result<Keymap, String> {
+        let config = parse(config)?;
+        let mut bindings = Keymap::default().bindings;
+        bindings.retain(|(_, command)| !config.contains_key(command));
+        for command in Command::ALL.iter() {
+            if let Some(keys) = config.get(command) {
+                bindings.extend(keys.iter().map(|key| (*key, *command)));
+            }
+        }
+
+        let keymap = Keymap { bindings };
+        let problems = keymap.problems();
+        if problems.is_empty() {
+            Ok(keymap)
+        } else {
+            Err(problems.join(" "))
+        }
+    }
+
+    /// Key bindings from the config file at `path`, the default ones if
+    /// there is none.
+    pub fn load(path: &Path) -> Result<Keymap, String> {
+        match fs::read_to_string(path) {
+            Ok(config) => Keymap::from_config(&config)
+                .map_err(|e| format!("Bad key bindings in {}: {}", path.display(), e)),
+            Err(ref e) if e.kind() == ErrorKind::NotFound => Ok(Keymap::default()),
+            Err(e) => Err(format!("Can't read {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Keys bound to more than one command on the same screen, and commands
+    /// without a key.
+    fn problems(&self) -> Vec<String> {
+        let shares_a_screen =
+            |a: Command, b: Command| a.screens().iter().any(|s| b.screens().contains(s));
+        let mut problems = vec![];
+        for (i, (key, command)) in self.bindings.iter().enumerate() {
+            let first = self
+                .bindings
+                .iter()
+                .position(|(k, c)| k == key && shares_a_screen(*c, *command));
+            if first != Some(i) {
+                continue;
+            }
+            if let Some((_, other)) = self.bindings[i + 1..]
+                .iter()
+                .find(|(k, c)| k == key && c != command && shares_a_screen(*c, *command))
+            {
+                problems.push(format!(
+                    "{} is bound to both {:?} and {:?}.",
+                    key, command, other
+                ));
+            }
+        }
+        for command in Command::ALL.iter() {
+            if self.keys(*command).is_empty() {
+                problems.push(format!("{:?} has no key.", command));
+            }
+        }
+        problems
+    }
+
+    /// Command bound to `key` on `screen`, if any.
+    pub fn command(&self, screen: Screen, key: Key) -> Option<Command> {
+        self.bindings
+            .iter()
+            .find(|(bound, command)| *bound == key && command.screens().contains(&screen))
+            .map(|(_, command)| *command)
+    }
+
+    /// Every key bound to `command`.
+    pub fn keys(&self, command: Command) -> Vec<Key> {
+        self.bindings
+            .iter()
+            .filter(|(_, bound)| *bound == command)
+            .map(|(key, _)| *key)
+            .collect()
+    }
+
+    /// Keys bound to `command` as shown in prompts, like "q/Esc".
+    pub fn hint(&self, command: Command) -> String {
+        let keys: Vec<String> = self.keys(command).iter().map(Key::to_string).collect();
+        keys.join("/")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_command_has_a_default_key() {
+        let keymap = Keymap::default();
+        for command in Command::ALL.iter() {
+            assert!(!keymap.keys(*command).is_empty(), "{:?}", command);
+        }
+    }
+
+    #[test]
+    fn test_keys_are_looked_up() {
+        let keymap = Keymap::default();
+        assert_eq!(
+            keymap.command(Screen::Level, Key::Char('k')),
+            Some(Command::MoveUp)
+        );
+        assert_eq!(keymap.command(Screen::Level, Key::Char('z')), None);
+        assert_eq!(
+            keymap.keys(Command::PickUp),
+            [Key::Char('g'), Key::Char(',')]
+        );
+        assert_eq!(Key::Ctrl('p').to_string(), "Ctrl-p");
+    }
+
+    #[test]
+    fn test_keys_are_read_from_their_names() {
+        assert_eq!("k".parse(), Ok(Key::Char('k')));
+        assert_eq!("Ctrl-p".parse(), Ok(Key::Ctrl('p')));
+        assert_eq!("PageDown".parse(), Ok(Key::PageDown));
+        assert!("Hyper-x".parse::<Key>().is_err());
+        assert!("".parse::<Key>().is_err());
+        assert_eq!("BackTab".parse(), Ok(Key::BackTab));
+    }
+
+    #[test]
+    fn test_prompts_read_keys_of_their_own() {
+        let keymap = Keymap::default();
+        assert_eq!(
+            keymap.command(Screen::History, Key::Char('k')),
+            Some(Command::ScrollUp)
+        );
+        assert_eq!(
+            keymap.command(Screen::Travel, Key::Char('k')),
+            Some(Command::MoveUp)
+        );
+        assert_eq!(
+            keymap.command(Screen::TakeOff, Key::Char('w')),
+            Some(Command::TakeOffWeapon)
+        );
+        assert_eq!(
+            keymap.command(Screen::GameOver, Key::Char('n')),
+            Some(Command::NewGame)
+        );
+        assert_eq!(
+            keymap.command(Screen::Look, Key::Char('n')),
+            Some(Command::MoveDownRight)
+        );
+        assert_eq!(keymap.command(Screen::Level, Key::Tab), None);
+        assert_eq!(keymap.hint(Command::Confirm), "./Enter");
+    }
+
+    #[test]
+    fn test_config_rebinds_only_the_commands_it_lists() {
+        let keymap = Keymap::from_config("{ MoveLeft: [\"q\"], Quit: [\"Ctrl-q\"] }").unwrap();
+        assert_eq!(
+            keymap.command(Screen::Level, Key::Char('q')),
+            Some(Command::MoveLeft)
+        );
+        assert_eq!(keymap.keys(Command::Quit), [Key::Ctrl('q')]);
+        assert_eq!(keymap.command(Screen::Level, Key::Char('h')), None);
+        assert_eq!(
+            keymap.keys(Command::MoveUp),
+            [Key::Char('k'), Key::Up, Key::Char('8')]
+        );
+    }
+
+    #[test]
+    fn test_config_conflicts_are_reported() {
+        let info = Keymap::from_config("{ MoveLeft: [\"q\"], Help: [] }")
+            .err()
+            .unwrap();
+        assert_eq!(
+            info,
+            "q is bound to both Quit and MoveLeft. Help has no key."
+        );
+        assert!(Keymap::from_config("{ Jump: [\"x\"] }").is_err());
+
+        // Prompts only clash with the commands read on the same screen.
+        assert!(Keymap::from_config("{ NextTarget: [\"q\"] }").is_ok());
+        assert_eq!(
+            Keymap::from_config("{ Cancel: [\"q\"] }").err().unwrap(),
+            "q is bound to both Quit and Cancel."
+        );
+        assert_eq!(
+            Keymap::from_config("{ Confirm: [\"k\"] }").err().unwrap(),
+            "k is bound to both MoveUp and Confirm."
+        );
+    }
+}
@@ -0,0 +1,15 @@
+pub mod bestiary;
+pub mod classes;
+pub mod entities;
+pub mod events;
+pub mod game;
+pub mod items;
+pub mod keymap;
+pub mod messages;
+pub mod morgue;
+pub mod save;
+pub mod scores;
+pub mod state;
+pub mod tiling;
+pub mod viewport;
+pub mod world;
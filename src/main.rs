@@ -1,71 +1,250 @@
-mod entities;
-mod events;
-mod state;
-mod tiling;
-mod viewport;
-mod world;
-
-use ignore_result::Ignore;
+use clap::{crate_version, value_t_or_exit, App, Arg, ErrorKind};
 use simplelog::*;
 use std::env;
 use std::fs::File;
+use std::path::PathBuf;
+use std::process;
+use std::str::FromStr;
 
-use entities::Player;
-use events::ViewportEvent;
-use state::State;
-use viewport::{CrossTermViewPort, ViewPort};
-use world::Dungeon;
+use roguelike::classes::{self, Class};
+use roguelike::game::{self, Ending, GameFiles};
+use roguelike::keymap::{self, Keymap};
+use roguelike::morgue;
+use roguelike::save;
+use roguelike::scores;
+use roguelike::state::State;
+use roguelike::viewport::{CrossTermViewPort, ViewPort};
+use roguelike::world::{self, Dungeon};
 
 const DUNGEON_SIZE_X: usize = 80;
 const DUNGEON_SIZE_Y: usize = 24;
 const DUNGEON_DEPTH: usize = 5;
 
-fn player_name() -> String {
+/// Settings of a new game, picked on the command line.
+struct Options {
+    /// Seed of every new game, a random one each time if not given
+    seed: Option<u64>,
+    xsize: usize,
+    ysize: usize,
+    depth: usize,
+    name: String,
+    /// Class of the player, asked at startup if not given
+    class: Option<Class>,
+    /// Where to write the debug log, no logging if not given
+    log_file: Option<String>,
+    /// Print the high scores instead of playing
+    show_scores: bool,
+}
+
+fn default_name() -> String {
     match env::var_os("USER") {
         Some(val) => val.into_string().unwrap(),
         None => String::from("Kshar"),
     }
 }
 
-fn main() {
-    // Set up the debug logger only if required.
-    if let Ok(_val) = env::var("DEBUG") {
-        WriteLogger::init(
-            LevelFilter::Debug,
-            Config::default(),
-            File::create("roguerust.log").unwrap(),
+/// Clap validator for numeric arguments.
+fn is_number<T: FromStr>(value: String) -> Result<(), String> {
+    value
+        .parse::<T>()
+        .map(|_| ())
+        .map_err(|_| format!("{} isn't a valid number", value))
+}
+
+/// Read the command line, exits with a message if it is wrong or if help
+/// was asked.
+fn parse_options() -> Options {
+    let classes = classes::all();
+    let class_names: Vec<&str> = classes.iter().map(|c| c.name.as_str()).collect();
+    let default_name = default_name();
+    let default_width = DUNGEON_SIZE_X.to_string();
+    let default_height = DUNGEON_SIZE_Y.to_string();
+    let default_depth = DUNGEON_DEPTH.to_string();
+
+    let matches = App::new("roguerust")
+        .version(crate_version!())
+        .about("A text-only old-school roguelike.")
+        .after_help("Options only apply to new games, a saved game resumes as it was left.")
+        .arg(
+            Arg::with_name("seed")
+                .long("seed")
+                .value_name("SEED")
+                .help("Seed of the dungeon, to replay the same one [default: random]")
+                .validator(is_number::<u64>),
+        )
+        .arg(
+            Arg::with_name("width")
+                .long("width")
+                .value_name("TILES")
+                .help("Width of the levels")
+                .default_value(&default_width)
+                .validator(is_number::<usize>),
+        )
+        .arg(
+            Arg::with_name("height")
+                .long("height")
+                .value_name("TILES")
+                .help("Height of the levels")
+                .default_value(&default_height)
+                .validator(is_number::<usize>),
         )
-        .unwrap();
+        .arg(
+            Arg::with_name("depth")
+                .long("depth")
+                .value_name("LEVELS")
+                .help("Number of levels of the dungeon")
+                .default_value(&default_depth)
+                .validator(is_number::<usize>),
+        )
+        .arg(
+            Arg::with_name("name")
+                .long("name")
+                .value_name("NAME")
+                .help("Name of the player")
+                .default_value(&default_name),
+        )
+        .arg(
+            Arg::with_name("class")
+                .long("class")
+                .value_name("CLASS")
+                .help("Class of the player [default: asked at startup]")
+                .possible_values(&class_names)
+                .case_insensitive(true),
+        )
+        .arg(
+            Arg::with_name("log-file")
+                .long("log-file")
+                .value_name("FILE")
+                .help("Write debug logs to FILE"),
+        )
+        .arg(
+            Arg::with_name("scores")
+                .long("scores")
+                .help("Print the high scores and exit"),
+        )
+        .get_matches();
+
+    let xsize = value_t_or_exit!(matches, "width", usize);
+    let ysize = value_t_or_exit!(matches, "height", usize);
+    let depth = value_t_or_exit!(matches, "depth", usize);
+    if let Err(info) = world::check_size(xsize, ysize, depth) {
+        clap::Error::with_description(&info, ErrorKind::InvalidValue).exit();
+    }
+
+    Options {
+        seed: if matches.is_present("seed") {
+            Some(value_t_or_exit!(matches, "seed", u64))
+        } else {
+            None
+        },
+        xsize,
+        ysize,
+        depth,
+        name: matches.value_of("name").unwrap().to_string(),
+        class: matches.value_of("class").and_then(classes::find),
+        log_file: matches.value_of("log-file").map(String::from),
+        show_scores: matches.is_present("scores"),
     }
+}
 
+/// Start a new game, asking the player for a class if none was chosen.
+/// `None` if they quit instead.
+fn new_game(window: &mut dyn ViewPort, options: &Options) -> Option<State> {
+    let class = match &options.class {
+        Some(class) => class.clone(),
+        None => {
+            let mut classes = classes::all();
+            let choice = window.select_class(&classes)?;
+            classes.swap_remove(choice)
+        }
+    };
     let mut state = State::new(
-        Player::new(player_name(), String::from("Warrior"), 30, 10, 10, 20),
-        Dungeon::new(DUNGEON_SIZE_X, DUNGEON_SIZE_Y, DUNGEON_DEPTH),
+        class.create(options.name.clone()),
+        Dungeon::new(
+            options.xsize,
+            options.ysize,
+            options.depth,
+            options.seed.unwrap_or_else(rand::random),
+        ),
     );
-    let mut window = CrossTermViewPort::new();
     state.init();
+    Some(state)
+}
 
-    // Main loop, dispatches events and calls rendering routines. Don't
-    // add any game logic here.
-    loop {
-        window.render_state(&state);
-
-        if let Some(event) = window.wait_input() {
-            match event {
-                ViewportEvent::Quit => break,
-                ViewportEvent::MovePlayer(direction) => state.move_player(direction).ignore(),
-                ViewportEvent::DownStairs => match state.down_stairs() {
-                    Ok(()) => (),
-                    Err(info) => window.notify(info),
-                },
-                ViewportEvent::UpStairs => match state.up_stairs() {
-                    Ok(()) => (),
-                    Err(info) => window.notify(info),
-                },
-                _ => (),
+/// Print the high score table, exits with an error if it can't be read.
+fn print_scores(path: Option<PathBuf>) {
+    let table = match path {
+        Some(path) => scores::load(&path),
+        None => Err(String::from("No data directory to keep high scores in")),
+    };
+    match table {
+        Ok(table) => {
+            for line in scores::table_lines(&table, None) {
+                println!("{}", line);
             }
         }
+        Err(info) => {
+            eprintln!("{}", info);
+            process::exit(1);
+        }
+    }
+}
+
+fn main() {
+    let options = parse_options();
+    let files = GameFiles {
+        save: save::save_path(),
+        scores: scores::scores_path(),
+        morgue: morgue::morgue_dir(),
+    };
+
+    if options.show_scores {
+        print_scores(files.scores);
+        return;
+    }
+
+    // Set up the debug logger only if required.
+    if let Some(path) = &options.log_file {
+        let file = File::create(path).unwrap_or_else(|e| {
+            clap::Error::with_description(&format!("Can't create {}: {}", path, e), ErrorKind::Io)
+                .exit()
+        });
+        WriteLogger::init(LevelFilter::Debug, Config::default(), file).unwrap();
+    }
+
+    // Bad key bindings are reported before taking over the terminal.
+    let keymap = match keymap::keymap_path() {
+        Some(path) => Keymap::load(&path).unwrap_or_else(|info| {
+            clap::Error::with_description(&info, ErrorKind::InvalidValue).exit()
+        }),
+        None => Keymap::default(),
+    };
 
-        // actors actions (normally attack / interact if on same location as the character)
+    // Resume the saved game if there is one.
+    let mut window = CrossTermViewPort::new(keymap);
+    let (mut resumed, mut greeting) = match save::load_game(&files.save) {
+        Ok(Some(state)) => match save::delete_game(&files.save) {
+            Ok(()) => (Some(state), String::from("Welcome back!")),
+            Err(info) => (Some(state), info),
+        },
+        Ok(None) => (None, String::from("Welcome! Press ? for help.")),
+        Err(info) => (None, info),
+    };
+
+    // Keep playing new games as long as the player wants to after dying.
+    loop {
+        let mut state = match resumed.take() {
+            Some(state) => state,
+            None => match new_game(&mut window, &options) {
+                Some(state) => state,
+                None => break,
+            },
+        };
+        state.log(greeting);
+
+        match game::run(&mut state, &mut window, &files) {
+            Ending::NewGame => greeting = String::from("Welcome back from the dead!"),
+            Ending::Quit | Ending::Saved => break,
+        }
     }
 }
@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+
+/// How many messages are remembered, older ones are forgotten.
+const MAX_MESSAGES: usize = 1000;
+
+/// Everything that happened to the player, oldest first.
+#[derive(Default, Serialize, Deserialize)]
+pub struct MessageLog {
+    lines: Vec<String>,
+}
+
+impl MessageLog {
+    pub fn new() -> MessageLog {
+        MessageLog { lines: vec![] }
+    }
+
+    pub fn add(&mut self, message: String) {
+        self.lines.push(message);
+        if self.lines.len() > MAX_MESSAGES {
+            let excess = self.lines.len() - MAX_MESSAGES;
+            self.lines.drain(..excess);
+        }
+    }
+
+    /// Every message remembered, oldest first.
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// The last `count` messages, oldest first.
+    pub fn recent(&self, count: usize) -> &[String] {
+        &self.lines[self.lines.len().saturating_sub(count)..]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recent_messages_are_the_last_ones() {
+        let mut log = MessageLog::new();
+        assert!(log.recent(3).is_empty());
+
+        for i in 0..5 {
+            log.add(format!("message {}", i));
+        }
+        assert_eq!(log.recent(2), ["message 3", "message 4"]);
+        assert_eq!(log.recent(10).len(), 5);
+    }
+
+    #[test]
+    fn test_old_messages_are_forgotten() {
+        let mut log = MessageLog::new();
+        for i in 0..MAX_MESSAGES + 10 {
+            log.add(format!("message {}", i));
+        }
+        assert_eq!(log.lines().len(), MAX_MESSAGES);
+        assert_eq!(log.lines()[0], "message 10");
+    }
+}
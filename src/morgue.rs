@@ -0,0 +1,188 @@
+use chrono::Local;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::iter;
+use std::path::{Path, PathBuf};
+
+use crate::entities::{Entity, Player};
+use crate::items;
+use crate::state::State;
+use crate::tiling::{tile_to_str, TileGrid, TileType};
+
+/// How many of the last messages go in a morgue file.
+const MORGUE_MESSAGES: usize = 20;
+
+/// Default directory of the morgue files, in the user's data directory.
+pub fn morgue_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("roguerust").join("morgue"))
+}
+
+/// Tiles of `grid` the player can walk on, and how many of them were
+/// explored.
+fn explored(grid: &TileGrid) -> (usize, usize) {
+    let walkable = grid.raw_data().iter().flatten().filter(|tile| {
+        matches!(
+            tile.get_type(),
+            TileType::Floor | TileType::Door | TileType::StairsUp | TileType::StairsDown
+        )
+    });
+    walkable.fold((0, 0), |(seen, total), tile| {
+        (seen + tile.is_visible() as usize, total + 1)
+    })
+}
+
+/// The current level as the player last saw it.
+fn map_lines(state: &State) -> Vec<String> {
+    let grid = match state.get_grid() {
+        Some(g) => g,
+        None => return vec![],
+    };
+    let mut map: Vec<Vec<&str>> = grid
+        .raw_data()
+        .iter()
+        .map(|row| row.iter().map(tile_to_str).collect())
+        .collect();
+
+    let player = state.get_player() as &dyn Entity;
+    for entity in state.floor_first().chain(iter::once(player)) {
+        if !entity.is_visible() {
+            continue;
+        }
+        let (x, y) = *entity.location();
+        map[y][x] = tile_to_str(entity.tile());
+    }
+
+    map.iter()
+        .map(|row| row.concat().trim_end().to_string())
+        .collect()
+}
+
+/// Plain text account of the game in `state`.
+pub fn dump(state: &State) -> String {
+    let player = state.get_player();
+    let mut lines = vec![
+        format!("{} the {}", player.name, player.class),
+        state.cause_of_death().unwrap_or("Still alive").to_string(),
+        String::new(),
+        player.stats(),
+        format!(
+            "Seed: {}  Deepest level: {}  Turns: {}  Kills: {}",
+            state.seed(),
+            state.deepest(),
+            state.turns(),
+            state.kills()
+        ),
+        String::new(),
+    ];
+    lines.extend(items::inventory_lines(
+        player.inventory(),
+        player.equipment(),
+    ));
+
+    lines.push(String::new());
+    lines.push(String::from("Levels"));
+    for (i, (stats, level)) in state
+        .level_stats()
+        .iter()
+        .zip(state.levels())
+        .enumerate()
+        .take(state.deepest())
+    {
+        let (seen, total) = explored(&level.grid);
+        lines.push(format!(
+            "  {:>2}: {} turns, {} kills, {}% explored",
+            i + 1,
+            stats.turns,
+            stats.kills,
+            100 * seen / total.max(1)
+        ));
+    }
+
+    lines.push(String::new());
+    lines.push(format!("Level {}", state.depth()));
+    lines.extend(map_lines(state));
+
+    lines.push(String::new());
+    lines.push(String::from("Last messages"));
+    lines.extend(
+        state
+            .messages()
+            .recent(MORGUE_MESSAGES)
+            .iter()
+            .map(|line| format!("  {}", line)),
+    );
+
+    lines.join("\n") + "\n"
+}
+
+/// Write the morgue file of the game in `state` to `dir`, named after the
+/// player and the current time. Existing files are never overwritten.
+/// Returns where it was written.
+pub fn write(dir: &Path, state: &State) -> Result<PathBuf, String> {
+    fs::create_dir_all(dir).map_err(|e| format!("Can't create {}: {}", dir.display(), e))?;
+
+    let name: String = state
+        .get_player()
+        .name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    let stem = format!("{}-{}", name, Local::now().format("%Y%m%d-%H%M%S"));
+    let data = dump(state);
+
+    // Games ending in the same second get numbered rather than overwritten.
+    let mut attempt = 1;
+    loop {
+        let path = match attempt {
+            1 => dir.join(format!("{}.txt", stem)),
+            _ => dir.join(format!("{}-{}.txt", stem, attempt)),
+        };
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                file.write_all(data.as_bytes())
+                    .map_err(|e| format!("Can't write {}: {}", path.display(), e))?;
+                return Ok(path);
+            }
+            Err(ref e) if e.kind() == ErrorKind::AlreadyExists => attempt += 1,
+            Err(e) => return Err(format!("Can't write {}: {}", path.display(), e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::test_state;
+    use std::env;
+
+    #[test]
+    fn test_dump_describes_the_game() {
+        let mut state = test_state();
+        state.log(String::from("Last words"));
+
+        let dump = dump(&state);
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(lines[0], "Tester the Warrior");
+        assert!(lines.contains(&"Seed: 42  Deepest level: 1  Turns: 0  Kills: 0"));
+        assert!(lines
+            .iter()
+            .any(|line| line.starts_with("   1: 0 turns, 0 kills")));
+        assert!(lines.iter().any(|line| line.contains('@')));
+        assert_eq!(lines.last(), Some(&"  Last words"));
+    }
+
+    #[test]
+    fn test_games_ending_together_get_their_own_files() {
+        let dir = env::temp_dir().join("roguerust-test-morgue");
+        fs::remove_dir_all(&dir).ok();
+        let state = test_state();
+
+        let first = write(&dir, &state).unwrap();
+        let second = write(&dir, &state).unwrap();
+        let files = fs::read_dir(&dir).unwrap().count();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(files, 2);
+    }
+}
@@ -0,0 +1,140 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use crate::state::State;
+
+/// Version of the save format, bump it whenever `State` changes shape or
+/// meaning so old saves get rejected instead of misread.
+const SAVE_VERSION: u32 = 12;
+
+const SAVE_FILE: &str = "roguerust.sav";
+
+#[derive(Serialize)]
+struct SaveFile<'a> {
+    version: u32,
+    state: &'a State,
+}
+
+#[derive(Deserialize)]
+struct SaveHeader {
+    version: u32,
+}
+
+#[derive(Deserialize)]
+struct LoadFile {
+    state: State,
+}
+
+/// Default place of the saved game, in the user's data directory, or the
+/// current directory if there is none.
+pub fn save_path() -> PathBuf {
+    dirs::data_dir()
+        .map(|dir| dir.join("roguerust"))
+        .unwrap_or_default()
+        .join(SAVE_FILE)
+}
+
+/// Write the whole game state to `path`.
+pub fn save_game(state: &State, path: &Path) -> Result<(), String> {
+    let save = SaveFile {
+        version: SAVE_VERSION,
+        state,
+    };
+    let data = ron::ser::to_string(&save).map_err(|e| format!("Can't save game: {}", e))?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Can't create {}: {}", dir.display(), e))?;
+    }
+    fs::write(path, data).map_err(|e| format!("Can't write {}: {}", path.display(), e))
+}
+
+/// Read a saved game from `path`, `None` if there is no saved game.
+pub fn load_game(path: &Path) -> Result<Option<State>, String> {
+    let data = match fs::read_to_string(path) {
+        Ok(data) => data,
+        Err(ref e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(format!("Can't read {}: {}", path.display(), e)),
+    };
+
+    let header: SaveHeader =
+        ron::de::from_str(&data).map_err(|e| format!("Corrupted save file: {}", e))?;
+    if header.version != SAVE_VERSION {
+        return Err(format!(
+            "Save file version {} isn't supported (expected {})",
+            header.version, SAVE_VERSION
+        ));
+    }
+
+    let save: LoadFile =
+        ron::de::from_str(&data).map_err(|e| format!("Corrupted save file: {}", e))?;
+    Ok(Some(save.state))
+}
+
+/// Remove the saved game at `path`, once a game is resumed it can't be
+/// resumed again.
+pub fn delete_game(path: &Path) -> Result<(), String> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(ref e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(format!("Can't remove {}: {}", path.display(), e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::{Entity, Player};
+    use crate::state::test_state;
+    use std::env;
+
+    fn temp_save(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("roguerust-test-{}.sav", name))
+    }
+
+    #[test]
+    fn test_missing_save_loads_nothing() {
+        let path = temp_save("missing");
+        delete_game(&path).unwrap();
+        assert!(load_game(&path).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_saved_game_is_restored() {
+        let path = temp_save("roundtrip");
+        let state = test_state();
+
+        save_game(&state, &path).unwrap();
+        let loaded = load_game(&path).unwrap().unwrap();
+        delete_game(&path).unwrap();
+
+        assert_eq!(loaded.seed(), state.seed());
+        assert_eq!(loaded.player.location(), state.player.location());
+        assert_eq!(loaded.player.stats(), state.player.stats());
+        assert!(loaded.get_grid() == state.get_grid());
+        assert_eq!(loaded.levels().len(), state.levels().len());
+        for (a, b) in loaded.levels().iter().zip(state.levels()) {
+            assert_eq!(a.entities.len(), b.entities.len());
+        }
+    }
+
+    #[test]
+    fn test_saving_creates_the_save_directory() {
+        let dir = env::temp_dir().join("roguerust-test-save-dir");
+        fs::remove_dir_all(&dir).ok();
+        let path = dir.join(SAVE_FILE);
+
+        save_game(&test_state(), &path).unwrap();
+        assert!(path.exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_other_save_versions_are_rejected() {
+        let path = temp_save("version");
+        fs::write(&path, "(version: 0, state: ())").unwrap();
+
+        assert!(load_game(&path).is_err());
+        delete_game(&path).unwrap();
+    }
+}
@@ -0,0 +1,266 @@
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use std::cmp;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::entities::Player;
+use crate::state::State;
+
+/// How many games the high score table remembers.
+pub const MAX_SCORES: usize = 10;
+
+const SCORES_FILE: &str = "scores.ron";
+
+/// Record of a finished game.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Score {
+    pub points: u64,
+    pub name: String,
+    pub class: String,
+    pub level: i32,
+    /// Deepest level reached
+    pub depth: usize,
+    pub kills: u32,
+    pub turns: u32,
+    pub cause: String,
+    pub seed: u64,
+}
+
+impl Score {
+    /// Score of the game in `state`. Going deep is worth the most, then
+    /// kills and experience. How long the game lasted only breaks ties, see
+    /// `beats`, so dying quickly is never worth it.
+    pub fn from_state(state: &State) -> Score {
+        let player = state.get_player();
+        let points = 1000 * state.deepest() as u64
+            + 50 * u64::from(state.kills())
+            + 10 * cmp::max(0, player.xp()) as u64;
+
+        Score {
+            points,
+            name: player.name.clone(),
+            class: player.class.clone(),
+            level: player.level,
+            depth: state.deepest(),
+            kills: state.kills(),
+            turns: state.turns(),
+            cause: state.cause_of_death().unwrap_or("Quit").to_string(),
+            seed: state.seed(),
+        }
+    }
+
+    /// Whether this score ranks above `other`: more points, or as many
+    /// points in fewer turns.
+    pub fn beats(&self, other: &Score) -> bool {
+        self.points > other.points || (self.points == other.points && self.turns < other.turns)
+    }
+}
+
+/// Default place of the high score table, in the user's data directory.
+pub fn scores_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("roguerust").join(SCORES_FILE))
+}
+
+fn parse(data: &str) -> Result<Vec<Score>, String> {
+    if data.trim().is_empty() {
+        return Ok(vec![]);
+    }
+    ron::de::from_str(data).map_err(|e| format!("Corrupted high score file: {}", e))
+}
+
+fn read_locked(file: &mut File) -> Result<Vec<Score>, String> {
+    let mut data = String::new();
+    file.read_to_string(&mut data)
+        .map_err(|e| format!("Can't read high scores: {}", e))?;
+    parse(&data)
+}
+
+/// Read the high score table at `path`, best first. A missing table is
+/// empty.
+pub fn load(path: &Path) -> Result<Vec<Score>, String> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(ref e) if e.kind() == ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(format!("Can't read {}: {}", path.display(), e)),
+    };
+    file.lock_shared()
+        .map_err(|e| format!("Can't lock {}: {}", path.display(), e))?;
+    let scores = read_locked(&mut file);
+    file.unlock().ok();
+    scores
+}
+
+/// Add `score` to the high score table at `path`. Returns the new table,
+/// and the rank of `score` in it if it made it. The file is locked for the
+/// whole update so games ending at the same time don't overwrite each
+/// other's scores.
+pub fn record(path: &Path, score: Score) -> Result<(Vec<Score>, Option<usize>), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| format!("Can't create {}: {}", dir.display(), e))?;
+    }
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+        .map_err(|e| format!("Can't open {}: {}", path.display(), e))?;
+    file.lock_exclusive()
+        .map_err(|e| format!("Can't lock {}: {}", path.display(), e))?;
+
+    let result = update_locked(&mut file, score);
+    file.unlock().ok();
+    result.map_err(|e| format!("Can't update {}: {}", path.display(), e))
+}
+
+fn update_locked(file: &mut File, score: Score) -> Result<(Vec<Score>, Option<usize>), String> {
+    let mut scores = read_locked(file)?;
+
+    // Full ties go to the oldest game.
+    let rank = scores
+        .iter()
+        .position(|s| score.beats(s))
+        .unwrap_or(scores.len());
+    scores.insert(rank, score);
+    scores.truncate(MAX_SCORES);
+
+    let data = ron::ser::to_string(&scores).map_err(|e| e.to_string())?;
+    file.seek(SeekFrom::Start(0)).map_err(|e| e.to_string())?;
+    file.set_len(0).map_err(|e| e.to_string())?;
+    file.write_all(data.as_bytes()).map_err(|e| e.to_string())?;
+
+    Ok((scores, Some(rank).filter(|rank| *rank < MAX_SCORES)))
+}
+
+/// Lines showing the high score table, `highlight` marks one of the scores.
+pub fn table_lines(scores: &[Score], highlight: Option<usize>) -> Vec<String> {
+    if scores.is_empty() {
+        return vec![String::from("No high scores yet.")];
+    }
+    scores
+        .iter()
+        .enumerate()
+        .map(|(rank, score)| {
+            format!(
+                "{}{:>2}. {:>6}  {} the {}, level {}, depth {}, {} kills in {} turns. {}.",
+                if highlight == Some(rank) { ">" } else { " " },
+                rank + 1,
+                score.points,
+                score.name,
+                score.class,
+                score.level,
+                score.depth,
+                score.kills,
+                score.turns,
+                score.cause
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::test_state;
+    use std::env;
+
+    fn scores_file(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("roguerust-test-{}-scores.ron", name))
+    }
+
+    fn score(name: &str, points: u64) -> Score {
+        Score {
+            points,
+            name: String::from(name),
+            class: String::from("Warrior"),
+            level: 1,
+            depth: 1,
+            kills: 0,
+            turns: 10,
+            cause: String::from("Killed by a rat on level 1"),
+            seed: 0,
+        }
+    }
+
+    #[test]
+    fn test_score_doesnt_reward_dying_early() {
+        let mut state = test_state();
+        let early = Score::from_state(&state);
+        assert_eq!(early.points, 1000);
+
+        for _ in 0..500 {
+            state.enemies_turn();
+        }
+        let late = Score::from_state(&state);
+        assert_eq!(late.turns, 500);
+        assert_eq!(late.points, early.points);
+
+        // Doing as well in fewer turns ranks higher.
+        assert!(early.beats(&late));
+        assert!(!late.beats(&early));
+        assert!(!late.beats(&late));
+    }
+
+    #[test]
+    fn test_missing_table_is_empty() {
+        let path = scores_file("missing");
+        fs::remove_file(&path).ok();
+        assert!(load(&path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_scores_are_kept_best_first() {
+        let path = scores_file("order");
+        fs::remove_file(&path).ok();
+
+        assert_eq!(record(&path, score("a", 100)).unwrap().1, Some(0));
+        assert_eq!(record(&path, score("b", 300)).unwrap().1, Some(0));
+        assert_eq!(record(&path, score("c", 200)).unwrap().1, Some(1));
+        assert_eq!(record(&path, score("d", 200)).unwrap().1, Some(2));
+
+        let names: Vec<String> = load(&path).unwrap().into_iter().map(|s| s.name).collect();
+        fs::remove_file(&path).ok();
+        assert_eq!(names, ["b", "c", "d", "a"]);
+    }
+
+    #[test]
+    fn test_quicker_games_win_ties() {
+        let path = scores_file("ties");
+        fs::remove_file(&path).ok();
+
+        let mut slow = score("slow", 200);
+        slow.turns = 500;
+        record(&path, slow).unwrap();
+        assert_eq!(record(&path, score("quick", 200)).unwrap().1, Some(0));
+        assert_eq!(record(&path, score("better", 210)).unwrap().1, Some(0));
+
+        let names: Vec<String> = load(&path).unwrap().into_iter().map(|s| s.name).collect();
+        fs::remove_file(&path).ok();
+        assert_eq!(names, ["better", "quick", "slow"]);
+    }
+
+    #[test]
+    fn test_table_only_keeps_the_best_scores() {
+        let path = scores_file("full");
+        fs::remove_file(&path).ok();
+
+        for i in 0..MAX_SCORES {
+            record(&path, score("good", 100 + i as u64)).unwrap();
+        }
+        let (scores, rank) = record(&path, score("bad", 1)).unwrap();
+        fs::remove_file(&path).ok();
+
+        assert_eq!(scores.len(), MAX_SCORES);
+        assert_eq!(rank, None);
+        assert!(scores.iter().all(|s| s.name == "good"));
+    }
+
+    #[test]
+    fn test_table_lines_highlight_a_score() {
+        let lines = table_lines(&[score("a", 20), score("b", 10)], Some(1));
+        assert!(lines[0].starts_with("  1.     20  a the Warrior"));
+        assert!(lines[1].starts_with("> 2.     10  b the Warrior"));
+    }
+}
@@ -1,45 +1,139 @@
-use crate::entities::{Character, Entity};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+use crate::entities::{Behaviour, Character, Enemy, Entity, FloorItem, Player};
+use crate::items::{self, Effect, Slot};
+use crate::messages::MessageLog;
 use crate::tiling::{Tile, TileGrid, TileType};
-use crate::world::{apply_movement, Dungeon, Generatable, Level, Movement};
+use crate::world::{
+    apply_movement, direction, distance, is_diagonal, manhattan_distance, Dungeon, Generatable,
+    Level, Movement, Point, DIRECTIONS,
+};
+
+/// How many turns a scroll of fear keeps the enemies running.
+const FEAR_TURNS: u32 = 10;
+
+/// What happened on one level of the dungeon.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct LevelStats {
+    /// Turns spent on the level
+    pub turns: u32,
+    /// Enemies killed on the level
+    pub kills: u32,
+}
 
-const PLAYER_SIGHT: usize = 1;
+/// Where the player can travel to.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Destination {
+    DownStairs,
+    UpStairs,
+    Location(Point),
+}
 
+#[derive(Serialize, Deserialize)]
 pub struct State {
     pub player: Character,
     dungeon: Dungeon,
     level: usize,
-    grid: Option<TileGrid>,
+    /// Depth of the deepest level visited, starting at 1
+    deepest: usize,
+    messages: MessageLog,
+    turns: u32,
+    kills: u32,
+    level_stats: Vec<LevelStats>,
+    cause_of_death: Option<String>,
 }
 
 impl State {
     pub fn new(player: Character, dungeon: Dungeon) -> State {
+        let level_stats = vec![LevelStats::default(); dungeon.depth()];
         State {
             player,
             dungeon,
             level: 0,
-            grid: None,
+            deepest: 1,
+            messages: MessageLog::new(),
+            turns: 0,
+            kills: 0,
+            level_stats,
+            cause_of_death: None,
         }
     }
 
     pub fn init(&mut self) {
-        self.dungeon.generate();
+        let mut rng = self.dungeon.rng();
+        self.dungeon.generate(&mut rng);
         self.switch_level(0);
         self.player.place(self.current_level().start_point());
         self.fog_of_war();
     }
 
     pub fn get_grid(&self) -> Option<&TileGrid> {
-        self.grid.as_ref()
+        self.dungeon.levels.get(self.level).map(|level| &level.grid)
     }
 
     pub fn get_player(&self) -> &Character {
         &self.player
     }
 
+    /// Tell the player something happened.
+    pub fn log(&mut self, message: String) {
+        self.messages.add(message);
+    }
+
+    pub fn messages(&self) -> &MessageLog {
+        &self.messages
+    }
+
+    /// Number of turns played so far.
+    pub fn turns(&self) -> u32 {
+        self.turns
+    }
+
+    /// Number of enemies killed by the player.
+    pub fn kills(&self) -> u32 {
+        self.kills
+    }
+
+    /// Statistics of every level, visited or not, from the top one down.
+    pub fn level_stats(&self) -> &[LevelStats] {
+        &self.level_stats
+    }
+
+    /// Depth of the current level, starting at 1.
+    pub fn depth(&self) -> usize {
+        self.level + 1
+    }
+
+    /// Depth of the deepest level visited so far, starting at 1.
+    pub fn deepest(&self) -> usize {
+        self.deepest
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.player.health == 0
+    }
+
+    /// What killed the player, if they are dead.
+    pub fn cause_of_death(&self) -> Option<&str> {
+        self.cause_of_death.as_deref()
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.dungeon.seed()
+    }
+
+    /// Make `num_level` the current level. Levels keep their own grid, so
+    /// whatever was explored there is remembered.
     pub fn switch_level(&mut self, num_level: usize) {
         self.level = num_level;
-        self.grid = Some(self.current_level().to_tilegrid().unwrap());
-        self.fog_of_war();
+        self.deepest = self.deepest.max(self.depth());
+    }
+
+    /// Every level of the dungeon, from the top one down.
+    pub fn levels(&self) -> &[Level] {
+        &self.dungeon.levels
     }
 
     pub fn current_level(&self) -> &Level {
@@ -50,54 +144,644 @@ impl State {
         &mut self.dungeon.levels[self.level]
     }
 
+    /// Entities of the current level, items lying on the floor first so the
+    /// creatures standing on them are drawn on top.
+    pub fn floor_first(&self) -> impl Iterator<Item = &dyn Entity> {
+        let entities = &self.current_level().entities;
+        entities
+            .iter()
+            .filter(|e| e.as_item().is_some())
+            .chain(entities.iter().filter(|e| e.as_item().is_none()))
+            .map(|e| &**e)
+    }
+
     fn can_step_on(tile: &Tile) -> bool {
         match tile.get_type() {
-            TileType::Floor => true,
-            TileType::StairsDown => true,
-            TileType::StairsUp => true,
+            TileType::Floor | TileType::StairsDown | TileType::StairsUp => true,
+            TileType::Door => tile.is_open(),
             _ => false,
         }
     }
 
     pub fn fog_of_war(&mut self) {
+        let level = &mut self.dungeon.levels[self.level];
+        level
+            .grid
+            .clear_fog_of_war(self.player.location(), self.player.sight);
+
+        // Creatures are only seen while they stand in the light, items don't
+        // move so they stay on the map once seen.
+        for entity in level.entities.iter_mut() {
+            let loc = *entity.location();
+            let tile = level.grid.tile_at(loc.0, loc.1);
+            let seen = match entity.as_item() {
+                Some(_) => tile.is_visible(),
+                None => tile.is_lit(),
+            };
+            entity.visibility(seen);
+        }
+    }
+
+    /// Find the index of the creature standing at `location`, if any. Items
+    /// lying there don't count.
+    fn entity_at(&self, location: Point) -> Option<usize> {
+        self.current_level()
+            .entities
+            .iter()
+            .position(|e| *e.location() == location && e.as_item().is_none())
+    }
+
+    /// Find the index of the first item lying at `location`, if any.
+    fn item_at(&self, location: Point) -> Option<usize> {
+        self.current_level()
+            .entities
+            .iter()
+            .position(|e| *e.location() == location && e.as_item().is_some())
+    }
+
+    /// Describe the items lying under the player, if any.
+    fn items_here(&self) -> Option<String> {
+        let location = *self.player.location();
+        let names: Vec<String> = self
+            .current_level()
+            .entities
+            .iter()
+            .filter(|e| *e.location() == location)
+            .filter_map(|e| e.as_item())
+            .map(|item| item.name.clone())
+            .collect();
+
+        if names.is_empty() {
+            None
+        } else {
+            Some(format!("You see here: {}.", names.join(", ")))
+        }
+    }
+
+    /// Resolve a single blow from `attacker` to `defender`. The chance to hit
+    /// is driven by the attacker's attack against the defender's dodge.
+    fn strike(attacker: &Character, defender: &mut Character) -> String {
+        let mut rng = rand::thread_rng();
+        let chance = (50 + attacker.attack() - defender.dodge()).clamp(5, 95);
+
+        if rng.gen_range(0, 100) >= chance {
+            return format!("{} misses {}.", attacker.name, defender.name);
+        }
+
+        let damage = rng.gen_range(1, (attacker.attack() / 2).max(0) + 2);
+        defender.damage(damage);
+        if defender.health == 0 {
+            format!("{} kills {}!", attacker.name, defender.name)
+        } else {
+            format!(
+                "{} hits {} for {} damage.",
+                attacker.name, defender.name, damage
+            )
+        }
+    }
+
+    /// The player attacks the enemy at index `target`. The enemy is removed
+    /// from the level if it dies, and the player earns experience.
+    fn melee(&mut self, target: usize) -> Result<Option<String>, String> {
+        let enemy = match self.dungeon.levels[self.level].entities[target].as_character_mut() {
+            Some(e) => e,
+            None => return Err(String::from("Can't attack that!")),
+        };
+
+        let mut message = State::strike(&self.player, enemy);
+        if enemy.health == 0 {
+            let xp = enemy.xp_value();
+            self.current_level_mut().entities.remove(target);
+            self.kills += 1;
+            self.level_stats[self.level].kills += 1;
+            if let Some(level_up) = self.player.gain_xp(xp) {
+                message = format!("{} {}", message, level_up);
+            }
+        }
+
+        Ok(Some(message))
+    }
+
+    /// Whether an actor could step on `location` right now.
+    fn is_free(&self, location: Point) -> bool {
+        let grid = match self.get_grid() {
+            Some(g) => g,
+            None => return false,
+        };
+
+        location.0 < grid.xsize()
+            && location.1 < grid.ysize()
+            && State::can_step_on(grid.tile_at(location.0, location.1))
+            && *self.player.location() != location
+            && self.entity_at(location).is_none()
+    }
+
+    /// Diagonal steps can't go through doorways, nor cut the corner of a
+    /// tile that can't be walked on. Straight steps are always fine.
+    fn check_diagonal(&self, from: Point, dir: Movement) -> Result<(), String> {
+        if !is_diagonal(dir) {
+            return Ok(());
+        }
+        let grid = match self.get_grid() {
+            Some(g) => g,
+            None => return Err(String::from("No level loaded!")),
+        };
+        let to = apply_movement(from, dir)?;
+        if to.0 >= grid.xsize() || to.1 >= grid.ysize() {
+            return Err(String::from("Can't move entity!"));
+        }
+
+        let is_door = |loc: Point| *grid.tile_at(loc.0, loc.1).get_type() == TileType::Door;
+        if is_door(from) || is_door(to) {
+            return Err(String::from("Doorways can't be crossed diagonally."));
+        }
+        let sides = [(to.0, from.1), (from.0, to.1)];
+        if sides
+            .iter()
+            .any(|loc| !State::can_step_on(grid.tile_at(loc.0, loc.1)))
         {
-            let grid = self.grid.as_mut().unwrap();
-            grid.clear_fog_of_war(self.player.location(), PLAYER_SIGHT);
+            return Err(String::from("You can't cut corners."));
         }
+        Ok(())
+    }
+
+    /// Free locations an actor standing at `from` can step to.
+    fn free_steps(&self, from: Point) -> impl Iterator<Item = Point> + '_ {
+        DIRECTIONS
+            .iter()
+            .filter(move |dir| self.check_diagonal(from, **dir).is_ok())
+            .filter_map(move |dir| apply_movement(from, *dir).ok())
+            .filter(move |loc| self.is_free(*loc))
+    }
+
+    /// Pick the free step from `from` that gets closest to `to`, if any gets
+    /// closer at all. Straighter steps win ties.
+    fn chase_step(&self, from: Point, to: Point) -> Option<Point> {
+        self.free_steps(from)
+            .filter(|loc| distance(*loc, to) < distance(from, to))
+            .min_by_key(|loc| (distance(*loc, to), manhattan_distance(*loc, to)))
+    }
 
+    /// Pick the free step from `from` that gets furthest from `to`, if any
+    /// gets further at all.
+    fn flee_step(&self, from: Point, to: Point) -> Option<Point> {
+        self.free_steps(from)
+            .filter(|loc| distance(*loc, to) > distance(from, to))
+            .max_by_key(|loc| (distance(*loc, to), manhattan_distance(*loc, to)))
+    }
+
+    /// Pick a random free step from `from`, or stay put half of the time.
+    fn wander_step(&self, from: Point) -> Option<Point> {
+        let mut rng = rand::thread_rng();
+        if rng.gen_bool(0.5) {
+            return None;
+        }
+
+        let dir = DIRECTIONS[rng.gen_range(0, DIRECTIONS.len())];
+        if self.check_diagonal(from, dir).is_err() {
+            return None;
+        }
+        apply_movement(from, dir)
+            .ok()
+            .filter(|loc| self.is_free(*loc))
+    }
+
+    /// Let the enemy at index `index` act: run away when afraid, attack the
+    /// player when adjacent, chase them when in sight, or wander around
+    /// otherwise. Monster quirks bend these rules.
+    fn enemy_turn(&mut self, index: usize) -> Option<String> {
+        // Only characters can act.
+        let enemy = self.current_level_mut().entities[index].as_character_mut()?;
+        let afraid = enemy.fear > 0 || (enemy.behaves(Behaviour::Cowardly) && enemy.badly_hurt());
+        let stationary = enemy.behaves(Behaviour::Stationary);
+        let erratic = enemy.behaves(Behaviour::Erratic);
+        enemy.fear = enemy.fear.saturating_sub(1);
+
+        let loc = *self.current_level().entities[index].location();
+        let target = *self.player.location();
+
+        let in_reach =
+            distance(loc, target) == 1 && self.check_diagonal(loc, direction(loc, target)).is_ok();
+        if !afraid && in_reach {
+            let enemy = self.dungeon.levels[self.level].entities[index].as_character_mut()?;
+            let message = State::strike(enemy, &mut self.player);
+            if self.player.health == 0 && self.cause_of_death.is_none() {
+                self.cause_of_death = Some(format!(
+                    "Killed by a {} on level {}",
+                    enemy.name,
+                    self.level + 1
+                ));
+            }
+            return Some(message);
+        }
+        if stationary {
+            return None;
+        }
+
+        // Sight is symmetrical, the enemy sees the player if the player
+        // lights the enemy's tile.
+        let sees_player = self.get_grid()?.tile_at(loc.0, loc.1).is_lit();
+        let step = if afraid {
+            self.flee_step(loc, target)
+        } else if sees_player && !(erratic && rand::thread_rng().gen_bool(0.5)) {
+            self.chase_step(loc, target)
+        } else {
+            self.wander_step(loc)
+        };
+
+        if let Some(dest) = step {
+            self.current_level_mut().entities[index].move_to(dest);
+        }
+        None
+    }
+
+    /// Every enemy on the current level acts once, which ends the turn. Meant
+    /// to be called after each player action. Once the player is dead the
+    /// others don't get to act.
+    pub fn enemies_turn(&mut self) -> Vec<String> {
+        self.turns += 1;
+        self.level_stats[self.level].turns += 1;
+        let mut messages = vec![];
         for i in 0..self.current_level().entities.len() {
-            let loc = *self.current_level().entities[i].location();
-            if self
-                .grid
-                .as_ref()
-                .unwrap()
-                .block_at(loc.0, loc.1)
-                .is_visible()
-                && !self.current_level().entities[i].is_visible()
-            {
-                self.current_level_mut().entities[i].visibility(true);
+            if self.player.health == 0 {
+                break;
+            }
+            if let Some(message) = self.enemy_turn(i) {
+                messages.push(message);
             }
         }
+        self.fog_of_war();
+        messages
     }
 
-    pub fn move_player(&mut self, dir: Movement) -> Result<(), String> {
-        let grid = match &self.grid {
+    pub fn move_player(&mut self, dir: Movement) -> Result<Option<String>, String> {
+        let grid = match self.get_grid() {
             Some(g) => g,
             None => return Err(String::from("No level loaded!")),
         };
 
         let loc = apply_movement(*self.player.location(), dir)?;
+        self.check_diagonal(*self.player.location(), dir)?;
+        // Bumping into a closed door opens it.
+        let tile = grid.tile_at(loc.0, loc.1);
+        if let TileType::Door = tile.get_type() {
+            if !tile.is_open() {
+                self.current_level_mut()
+                    .grid
+                    .tile_at_mut(loc.0, loc.1)
+                    .open();
+                self.fog_of_war();
+                return Ok(Some(String::from("You open the door.")));
+            }
+        }
         // Is the new location colliding with anything?
-        if !State::can_step_on(grid.block_at(loc.0, loc.1)) {
+        if !State::can_step_on(grid.tile_at(loc.0, loc.1)) {
             return Err(String::from("Can't move entity!"));
         }
-        let ret = self.player.move_by(dir);
+        if let Some(target) = self.entity_at(loc) {
+            return self.melee(target);
+        }
+        self.player.move_by(dir)?;
+        self.fog_of_war();
+        let items = self.items_here();
+        if items.is_some() {
+            self.current_level_mut().see_items(loc);
+        }
+        Ok(items)
+    }
+
+    /// Where the creatures in view stand, closest to the player first.
+    pub fn visible_monsters(&self) -> Vec<Point> {
+        let player = *self.player.location();
+        let mut monsters: Vec<Point> = self
+            .current_level()
+            .entities
+            .iter()
+            .filter(|e| e.is_visible() && e.as_character().is_some())
+            .map(|e| *e.location())
+            .collect();
+        monsters.sort_by_key(|loc| (distance(player, *loc), manhattan_distance(player, *loc)));
+        monsters
+    }
+
+    /// What the player knows of `location`: the tile, the creature standing
+    /// there if they can see it, and the items they saw lying there.
+    pub fn describe(&self, location: Point) -> String {
+        let grid = match self.get_grid() {
+            Some(g) => g,
+            None => return String::from("No level loaded!"),
+        };
+        let (x, y) = location;
+        if x >= grid.xsize() || y >= grid.ysize() || !grid.tile_at(x, y).is_visible() {
+            return String::from("You haven't seen this place.");
+        }
+        let tile = grid.tile_at(x, y);
+        let mut parts = vec![String::from(match tile.get_type() {
+            TileType::Wall => "A wall.",
+            TileType::Floor => "The floor.",
+            TileType::Door if tile.is_open() => "An open door.",
+            TileType::Door => "A closed door.",
+            TileType::StairsDown => "Stairs going down.",
+            TileType::StairsUp => "Stairs going up.",
+            _ => "Solid rock.",
+        })];
+
+        if *self.player.location() == location {
+            parts.push(String::from("You are standing here."));
+        }
+        let entities = &self.current_level().entities;
+        let here = || {
+            entities
+                .iter()
+                .filter(|e| *e.location() == location && e.is_visible())
+        };
+        for creature in here().filter(|e| e.as_character().is_some()) {
+            parts.push(format!("A {}.", creature.info()));
+        }
+        let items: Vec<String> = here()
+            .filter_map(|e| e.as_item())
+            .map(|item| item.name.clone())
+            .collect();
+        if !items.is_empty() {
+            parts.push(format!("Lying here: {}.", items.join(", ")));
+        }
+        parts.join(" ")
+    }
+
+    /// Name of an enemy the player can see, if any.
+    fn enemy_in_view(&self) -> Option<&str> {
+        self.current_level()
+            .entities
+            .iter()
+            .filter(|e| e.is_visible())
+            .find_map(|e| e.as_character())
+            .map(|c| c.name.as_str())
+    }
+
+    /// First step of the shortest walk from the player to a location where
+    /// `goal` holds. Only explored tiles are walked through, closed doors
+    /// included since bumping opens them, and creatures are walked around.
+    fn path_step<F: Fn(Point) -> bool>(&self, goal: F) -> Option<Movement> {
+        let grid = self.get_grid()?;
+        let start = *self.player.location();
+        let mut first_steps = vec![vec![None; grid.xsize()]; grid.ysize()];
+        let mut queue = VecDeque::new();
+        queue.push_back(start);
+
+        while let Some(loc) = queue.pop_front() {
+            for dir in DIRECTIONS.iter() {
+                let next = match apply_movement(loc, *dir) {
+                    Ok(next) if next.0 < grid.xsize() && next.1 < grid.ysize() => next,
+                    _ => continue,
+                };
+                let tile = grid.tile_at(next.0, next.1);
+                let walkable = State::can_step_on(tile) || *tile.get_type() == TileType::Door;
+                if next == start
+                    || first_steps[next.1][next.0].is_some()
+                    || !tile.is_visible()
+                    || !walkable
+                    || self.check_diagonal(loc, *dir).is_err()
+                    || self.entity_at(next).is_some()
+                {
+                    continue;
+                }
+
+                let first_step = if loc == start {
+                    *dir
+                } else {
+                    first_steps[loc.1][loc.0]?
+                };
+                if goal(next) {
+                    return Some(first_step);
+                }
+                first_steps[next.1][next.0] = Some(first_step);
+                queue.push_back(next);
+            }
+        }
+        None
+    }
+
+    /// Whether `location` was explored and some tile next to it wasn't, or
+    /// items the player hasn't stood on yet lie there.
+    fn worth_exploring(&self, location: Point) -> bool {
+        let grid = match self.get_grid() {
+            Some(g) => g,
+            None => return false,
+        };
+        let unexplored = DIRECTIONS
+            .iter()
+            .any(|dir| match apply_movement(location, *dir) {
+                Ok((x, y)) if x < grid.xsize() && y < grid.ysize() => {
+                    !grid.tile_at(x, y).is_visible()
+                }
+                _ => false,
+            });
+        let new_items =
+            self.item_at(location).is_some() && !self.current_level().items_seen_at(location);
+        unexplored || new_items
+    }
+
+    /// Take one step towards the closest place left to explore, unless an
+    /// enemy is in view. Walking onto an item tells what it is, which is
+    /// where exploring should stop.
+    pub fn explore(&mut self) -> Result<Option<String>, String> {
+        if let Some(name) = self.enemy_in_view() {
+            return Err(format!("There is a {} in view.", name));
+        }
+        let dir = match self.path_step(|loc| self.worth_exploring(loc)) {
+            Some(dir) => dir,
+            None => return Err(String::from("Nothing left to explore.")),
+        };
+
+        self.walk(dir)
+    }
+
+    /// Step in `dir` as part of a longer walk, opening the doors on the way
+    /// without a word as that's nothing to stop for.
+    fn walk(&mut self, dir: Movement) -> Result<Option<String>, String> {
+        let dest = apply_movement(*self.player.location(), dir)?;
+        let door = self
+            .get_grid()
+            .map(|grid| grid.tile_at(dest.0, dest.1).clone());
+        let message = self.move_player(dir)?;
+        match door {
+            Some(tile) if *tile.get_type() == TileType::Door && !tile.is_open() => Ok(None),
+            _ => Ok(message),
+        }
+    }
+
+    /// Where `destination` is on the current level, if the player knows.
+    fn locate(&self, destination: Destination) -> Result<Point, String> {
+        let (stairs, name) = match destination {
+            Destination::DownStairs => (TileType::StairsDown, "down"),
+            Destination::UpStairs => (TileType::StairsUp, "up"),
+            Destination::Location(location) => return Ok(location),
+        };
+        let grid = match self.get_grid() {
+            Some(g) => g,
+            None => return Err(String::from("No level loaded!")),
+        };
+        (0..grid.ysize())
+            .flat_map(|y| (0..grid.xsize()).map(move |x| (x, y)))
+            .find(|(x, y)| {
+                let tile = grid.tile_at(*x, *y);
+                tile.is_visible() && *tile.get_type() == stairs
+            })
+            .ok_or_else(|| format!("You don't know where the stairs {} are.", name))
+    }
+
+    /// Take one step on the shortest known way to `destination`, unless an
+    /// enemy is in view.
+    pub fn travel(&mut self, destination: Destination) -> Result<Option<String>, String> {
+        let target = self.locate(destination)?;
+        if *self.player.location() == target {
+            return Err(String::from("You are already there."));
+        }
+        if let Some(name) = self.enemy_in_view() {
+            return Err(format!("There is a {} in view.", name));
+        }
+        let dir = match self.path_step(|loc| loc == target) {
+            Some(dir) => dir,
+            None => return Err(String::from("You don't know the way there.")),
+        };
+
+        let message = self.walk(dir)?;
+        if *self.player.location() == target {
+            Ok(message.or_else(|| Some(String::from("You have arrived."))))
+        } else {
+            Ok(message)
+        }
+    }
+
+    /// Pick up the items lying under the player, as long as there is room for
+    /// them in the inventory.
+    pub fn pick_up(&mut self) -> Result<Option<String>, String> {
+        let location = *self.player.location();
+        if self.item_at(location).is_none() {
+            return Err(String::from("There is nothing here."));
+        }
+        if self.player.inventory_full() {
+            return Err(String::from("Your pack is full."));
+        }
+
+        let mut picked = vec![];
+        while let Some(index) = self.item_at(location) {
+            if self.player.inventory_full() {
+                break;
+            }
+            let entity = self.current_level_mut().entities.remove(index);
+            if let Some(item) = entity.as_item() {
+                picked.push(format!(
+                    "{}) {}",
+                    items::letter(self.player.inventory().len()),
+                    item
+                ));
+                self.player.give(item.clone());
+            }
+        }
+
+        let mut message = format!("You pick up {}.", picked.join(", "));
+        if self.item_at(location).is_some() {
+            message.push_str(" Your pack is full.");
+        }
+        Ok(Some(message))
+    }
+
+    /// Drink or read the inventory item at `index`, using it up.
+    pub fn use_item(&mut self, index: usize) -> Result<Option<String>, String> {
+        let effect = match self.player.inventory().get(index) {
+            Some(item) => item
+                .effect()
+                .ok_or_else(|| format!("You can't use the {}.", item.name))?,
+            None => return Err(String::from("No such item!")),
+        };
+        let item = self.player.take(index)?;
+
+        let outcome = match effect {
+            Effect::Heal(amount) => {
+                let health = self.player.health;
+                self.player.heal(amount);
+                format!("You heal {} hp.", self.player.health - health)
+            }
+            Effect::Teleport => self.teleport_player(),
+            Effect::MagicMapping => {
+                self.current_level_mut().grid.reveal_all();
+                self.fog_of_war();
+                String::from("A map of the level forms in your mind.")
+            }
+            Effect::Fear => self.frighten_enemies(),
+        };
+
+        Ok(Some(format!(
+            "You {} the {}. {}",
+            item.verb(),
+            item.name,
+            outcome
+        )))
+    }
+
+    /// Move the player to a random free spot of the current level.
+    fn teleport_player(&mut self) -> String {
+        let grid = match self.get_grid() {
+            Some(g) => g,
+            None => return String::from("Nothing happens."),
+        };
+        let spots: Vec<Point> = (0..grid.ysize())
+            .flat_map(|y| (0..grid.xsize()).map(move |x| (x, y)))
+            .filter(|loc| self.is_free(*loc))
+            .collect();
+        if spots.is_empty() {
+            return String::from("Nothing happens.");
+        }
+
+        let spot = spots[rand::thread_rng().gen_range(0, spots.len())];
+        self.player.place(spot);
         self.fog_of_war();
-        ret
+        String::from("You find yourself elsewhere.")
+    }
+
+    /// Make every enemy in sight run away for a while.
+    fn frighten_enemies(&mut self) -> String {
+        let mut fleeing = vec![];
+        for entity in self.current_level_mut().entities.iter_mut() {
+            if !entity.is_visible() {
+                continue;
+            }
+            if let Some(enemy) = entity.as_character_mut() {
+                enemy.fear = FEAR_TURNS;
+                fleeing.push(format!("The {} flees!", enemy.name));
+            }
+        }
+
+        if fleeing.is_empty() {
+            String::from("You hear a distant scream.")
+        } else {
+            fleeing.join(" ")
+        }
+    }
+
+    /// Drop the inventory item at `index` under the player.
+    pub fn drop(&mut self, index: usize) -> Result<Option<String>, String> {
+        let item = self.player.take(index)?;
+        let message = format!("You drop the {}.", item.name);
+        let location = *self.player.location();
+        self.current_level_mut()
+            .entities
+            .push(Box::new(FloorItem::new(item, location)));
+        self.fog_of_war();
+        Ok(Some(message))
+    }
+
+    pub fn equip(&mut self, index: usize) -> Result<Option<String>, String> {
+        self.player.equip(index).map(Some)
+    }
+
+    pub fn unequip(&mut self, slot: Slot) -> Result<Option<String>, String> {
+        self.player.unequip(slot).map(Some)
     }
 
     pub fn down_stairs(&mut self) -> Result<(), String> {
-        let grid = match &self.grid {
+        let grid = match self.get_grid() {
             Some(g) => g,
             None => return Err(String::from("No level loaded!")),
         };
@@ -107,9 +791,11 @@ impl State {
         }
 
         let loc = self.player.location();
-        match grid.block_at(loc.0, loc.1).get_type() {
+        match grid.tile_at(loc.0, loc.1).get_type() {
             TileType::StairsDown => {
                 self.switch_level(self.level + 1);
+                self.player.place(self.current_level().entrance());
+                self.fog_of_war();
                 Ok(())
             }
             _ => Err(String::from("Not on stairs!")),
@@ -117,7 +803,7 @@ impl State {
     }
 
     pub fn up_stairs(&mut self) -> Result<(), String> {
-        let grid = match &self.grid {
+        let grid = match self.get_grid() {
             Some(g) => g,
             None => return Err(String::from("No level loaded!")),
         };
@@ -127,12 +813,260 @@ impl State {
         }
 
         let loc = self.player.location();
-        match grid.block_at(loc.0, loc.1).get_type() {
+        match grid.tile_at(loc.0, loc.1).get_type() {
             TileType::StairsUp => {
                 self.switch_level(self.level - 1);
+                self.player.place(self.current_level().exit());
+                self.fog_of_war();
                 Ok(())
             }
             _ => Err(String::from("Not on stairs!")),
         }
     }
 }
+
+/// A warrior alone on the first level of a fixed dungeon, for tests.
+#[cfg(test)]
+pub(crate) fn test_state() -> State {
+    let player = crate::classes::find("Warrior")
+        .unwrap()
+        .create(String::from("Tester"));
+    let mut state = State::new(player, Dungeon::new(80, 24, 3, 42));
+    state.init();
+    state.current_level_mut().entities.clear();
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monster(health: i32, attack: i32, dodge: i32, location: Point) -> Character {
+        Enemy::new(String::from("rat"), health, attack, dodge, 0, location, "r")
+    }
+
+    /// A step from the player onto the floor, and where it leads.
+    fn step_to_floor(state: &State) -> (Movement, Point) {
+        let from = *state.player.location();
+        DIRECTIONS
+            .iter()
+            .filter(|dir| state.check_diagonal(from, **dir).is_ok())
+            .filter_map(|dir| apply_movement(from, *dir).ok().map(|loc| (*dir, loc)))
+            .find(|(_, loc)| state.is_free(*loc))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_strike_deals_damage_by_attack() {
+        let attacker = monster(10, 6, 0, (0, 0));
+        let mut defender = monster(1000, 0, -100, (1, 0));
+        let mut dealt = 0;
+        for _ in 0..100 {
+            let before = defender.health;
+            let message = State::strike(&attacker, &mut defender);
+            let damage = before - defender.health;
+            assert!((0..=4).contains(&damage), "{}", damage);
+            if damage == 0 {
+                assert_eq!(message, "rat misses rat.");
+            } else {
+                assert_eq!(message, format!("rat hits rat for {} damage.", damage));
+            }
+            dealt += damage;
+        }
+        assert!(dealt > 0);
+    }
+
+    #[test]
+    fn test_weak_attackers_still_deal_one_damage() {
+        let attacker = monster(10, -10, 0, (0, 0));
+        let mut defender = monster(1000, 0, -100, (1, 0));
+        for _ in 0..100 {
+            let before = defender.health;
+            State::strike(&attacker, &mut defender);
+            assert!(before - defender.health <= 1);
+        }
+        assert!(defender.health < 1000);
+    }
+
+    #[test]
+    fn test_melee_kills_and_removes_the_corpse() {
+        let mut state = test_state();
+        let (dir, target) = step_to_floor(&state);
+        state
+            .current_level_mut()
+            .entities
+            .push(Box::new(monster(1, 0, -100, target)));
+        let start = *state.player.location();
+
+        let mut message = None;
+        while state.entity_at(target).is_some() {
+            message = state.move_player(dir).unwrap();
+        }
+
+        assert_eq!(message.unwrap(), "Tester kills rat!");
+        assert!(state.current_level().entities.is_empty());
+        assert_eq!(state.kills(), 1);
+        assert_eq!(state.level_stats()[0].kills, 1);
+        assert_eq!(*state.player.location(), start);
+    }
+
+    /// Free floor tiles the player can see, `range` steps away from them.
+    fn lit_floor_at(state: &State, range: usize) -> Vec<Point> {
+        let grid = state.get_grid().unwrap();
+        let player = *state.player.location();
+        (0..grid.xsize())
+            .flat_map(|x| (0..grid.ysize()).map(move |y| (x, y)))
+            .filter(|loc| distance(*loc, player) == range)
+            .filter(|loc| grid.tile_at(loc.0, loc.1).is_lit() && state.is_free(*loc))
+            .collect()
+    }
+
+    #[test]
+    fn test_chasing_enemies_close_in() {
+        let mut state = test_state();
+        let player = *state.player.location();
+        let start = *lit_floor_at(&state, 2)
+            .iter()
+            .find(|loc| state.chase_step(**loc, player).is_some())
+            .unwrap();
+        state
+            .current_level_mut()
+            .entities
+            .push(Box::new(monster(5, 2, 0, start)));
+
+        assert_eq!(state.enemy_turn(0), None);
+
+        let loc = *state.current_level().entities[0].location();
+        assert_eq!(distance(loc, player), 1);
+    }
+
+    #[test]
+    fn test_enemies_only_step_on_free_floor() {
+        let mut state = test_state();
+        let player = *state.player.location();
+        let (_, next) = step_to_floor(&state);
+        state
+            .current_level_mut()
+            .entities
+            .push(Box::new(monster(5, 2, 0, next)));
+        let others: Vec<Point> = DIRECTIONS
+            .iter()
+            .filter_map(|dir| apply_movement(next, *dir).ok())
+            .filter(|loc| state.is_free(*loc))
+            .step_by(2)
+            .collect();
+        for loc in others.iter() {
+            state
+                .current_level_mut()
+                .entities
+                .push(Box::new(monster(5, 2, 0, *loc)));
+        }
+
+        let grid = state.get_grid().unwrap();
+        let mut stepped = false;
+        for _ in 0..100 {
+            let steps = state
+                .free_steps(next)
+                .chain(state.wander_step(next))
+                .chain(state.flee_step(next, player))
+                .chain(state.chase_step(next, player));
+            for step in steps {
+                assert!(State::can_step_on(grid.tile_at(step.0, step.1)));
+                assert_ne!(step, player);
+                assert!(!others.contains(&step));
+                stepped = true;
+            }
+        }
+        assert!(stepped);
+    }
+
+    #[test]
+    fn test_adjacent_enemies_attack_instead_of_moving() {
+        let mut state = test_state();
+        let (_, next) = step_to_floor(&state);
+        state
+            .current_level_mut()
+            .entities
+            .push(Box::new(monster(5, 2, 0, next)));
+
+        let message = state.enemy_turn(0).unwrap();
+
+        assert!(message.starts_with("rat hits Tester") || message == "rat misses Tester.");
+        assert_eq!(*state.current_level().entities[0].location(), next);
+    }
+
+    #[test]
+    fn test_enemies_stop_once_the_player_is_dead() {
+        let mut state = test_state();
+        let from = *state.player.location();
+        let around: Vec<Point> = DIRECTIONS
+            .iter()
+            .filter(|dir| state.check_diagonal(from, **dir).is_ok())
+            .filter_map(|dir| apply_movement(from, *dir).ok())
+            .filter(|loc| state.is_free(*loc))
+            .take(2)
+            .collect();
+        assert_eq!(around.len(), 2);
+        for loc in around {
+            state
+                .current_level_mut()
+                .entities
+                .push(Box::new(monster(5, 100, 0, loc)));
+        }
+
+        let messages = loop {
+            state.player.health = 1;
+            let messages = state.enemies_turn();
+            if state.is_dead() {
+                break messages;
+            }
+        };
+        let kill = messages.iter().position(|m| m == "rat kills Tester!");
+        assert_eq!(kill, Some(messages.len() - 1), "{:?}", messages);
+    }
+
+    /// Which tiles of the current level were explored, and which are open.
+    fn explored_map(state: &State) -> Vec<(bool, bool)> {
+        let grid = state.get_grid().unwrap();
+        grid.raw_data()
+            .iter()
+            .flatten()
+            .map(|tile| (tile.is_visible(), tile.is_open()))
+            .collect()
+    }
+
+    #[test]
+    fn test_levels_are_remembered_across_stairs() {
+        let mut state = test_state();
+        for _ in 0..30 {
+            state.explore().unwrap();
+        }
+        let exit = state.current_level().exit();
+        let grid = state.get_grid().unwrap();
+        let door = (0..grid.xsize())
+            .flat_map(|x| (0..grid.ysize()).map(move |y| (x, y)))
+            .find(|(x, y)| *grid.tile_at(*x, *y).get_type() == TileType::Door)
+            .unwrap();
+        state
+            .current_level_mut()
+            .grid
+            .tile_at_mut(door.0, door.1)
+            .open();
+        state.player.place(exit);
+        state.fog_of_war();
+        let explored = explored_map(&state);
+
+        state.down_stairs().unwrap();
+        assert_eq!(state.depth(), 2);
+        assert_ne!(explored_map(&state), explored);
+        state.up_stairs().unwrap();
+
+        assert_eq!(state.depth(), 1);
+        assert_eq!(*state.player.location(), exit);
+        assert_eq!(
+            *state.get_grid().unwrap().tile_at(exit.0, exit.1).get_type(),
+            TileType::StairsDown
+        );
+        assert_eq!(explored_map(&state), explored);
+    }
+}
@@ -1,18 +1,20 @@
+use serde::{Deserialize, Serialize};
 use std::convert::From;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum TileType {
     Empty,
     Wall,
     Floor,
     StairsUp,
     StairsDown,
-    Character(&'static str),
+    Character(String),
+    Item(String),
     Player,
     Door,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Tile {
     tile_type: TileType,
     visible: bool,
@@ -64,33 +66,34 @@ impl Tile {
 	self.open
     }
 
+    /// Open the tile, light goes through open tiles.
     pub fn open(&mut self) {
 	self.open = true;
+	self.opaque = false;
     }
 
     pub fn clos(&mut self) {
 	self.open = false;
+	self.opaque = true;
     }
 }
 
 impl From<TileType> for Tile {
     fn from(tile_type: TileType) -> Self {
+        let opaque = matches!(tile_type, TileType::Empty | TileType::Wall | TileType::Door);
         Tile {
             tile_type,
             visible: false, // <--- TODO: this set the default beaviour
             //            - true: all tiles of world and entities will be drawn
             //            - false: only draw tiles visible for the player
-            opaque: match tile_type {
-                TileType::Empty => true,
-                TileType::Wall => true,
-                _ => false,
-            },
+            opaque,
 	    lit: false,
 	    open: false,
         }
     }
 }
 
+#[derive(PartialEq, Serialize, Deserialize)]
 pub struct TileGrid {
     grid: Vec<Vec<Tile>>,
     xsize: usize,
@@ -145,6 +148,10 @@ impl TileGrid {
         &self.grid[y + 1][x]
     }
 
+    pub fn tile_at_mut(&mut self, x: usize, y: usize) -> &mut Tile {
+        &mut self.grid[y][x]
+    }
+
     pub fn xsize(&self) -> usize {
         self.xsize
     }
@@ -154,40 +161,122 @@ impl TileGrid {
     }
 
     fn reveal(&mut self, x: usize, y: usize) {
-        self.grid[y + 1][x].visibility(true);
+        self.grid[y][x].visibility(true);
     }
 
     fn light(&mut self, x: usize, y: usize) {
-	self.grid[y + 1][x].lit(true);
+	self.grid[y][x].lit(true);
+    }
+
+    /// Reveal the whole grid, as if it had all been explored.
+    pub fn reveal_all(&mut self) {
+        for x in 0..self.xsize {
+            for y in 0..self.ysize {
+                self.reveal(x, y);
+            }
+        }
     }
 
-    /// Walk around the perimeter of the line of sight and ray-trace to clear tiles
-    /// up to the nearest obstacle.
+    /// Reveal and light every tile in sight of `center` within `radius`, using
+    /// recursive shadowcasting so opaque tiles block the line of sight.
     pub fn clear_fog_of_war(&mut self, center: &(usize, usize), radius: usize) {
-	// Unlight everything first.
-	for x in 0..self.xsize {
-	    for y in 0..self.ysize {
-		self.grid[y][x].lit(false)
-	    }
-	}
+        // Unlight everything first.
+        for x in 0..self.xsize {
+            for y in 0..self.ysize {
+                self.grid[y][x].lit(false)
+            }
+        }
+
+        if center.0 >= self.xsize || center.1 >= self.ysize {
+            return;
+        }
+        self.reveal(center.0, center.1);
+        self.light(center.0, center.1);
+
+        for octant in OCTANTS.iter() {
+            self.cast_light(*center, 1, 1.0, 0.0, radius as i32, *octant);
+        }
+    }
 
-	let start: (usize, usize) = (center.0.saturating_sub(radius), center.1.saturating_sub(radius));
-	let end: (usize, usize) = (center.0.saturating_add(radius), center.1.saturating_add(radius));
+    /// Scan one octant row by row from `row`, between the `start` and `end`
+    /// slopes. Rows past an obstacle are scanned recursively with the slopes
+    /// narrowed to what the obstacle doesn't hide.
+    fn cast_light(
+        &mut self,
+        center: (usize, usize),
+        row: i32,
+        start: f32,
+        end: f32,
+        radius: i32,
+        octant: (i32, i32, i32, i32),
+    ) {
+        if start < end {
+            return;
+        }
 
-	println!("From {:?} to {:?}", start, end);
+        let (xx, xy, yx, yy) = octant;
+        let mut start = start;
+        let mut next_start = start;
+
+        for distance in row..=radius {
+            let dy = -distance;
+            let mut blocked = false;
+
+            for dx in -distance..=0 {
+                let left_slope = (dx as f32 - 0.5) / (dy as f32 + 0.5);
+                let right_slope = (dx as f32 + 0.5) / (dy as f32 - 0.5);
+                if start < right_slope {
+                    continue;
+                } else if end > left_slope {
+                    break;
+                }
+
+                let x = center.0 as i32 + dx * xx + dy * xy;
+                let y = center.1 as i32 + dx * yx + dy * yy;
+                let in_grid = x >= 0 && y >= 0 && x < self.xsize as i32 && y < self.ysize as i32;
+                if in_grid && dx * dx + dy * dy <= radius * (radius + 1) {
+                    self.reveal(x as usize, y as usize);
+                    self.light(x as usize, y as usize);
+                }
+
+                // Anything outside of the grid blocks the view.
+                let opaque = !in_grid || self.grid[y as usize][x as usize].is_opaque();
+                if blocked {
+                    if opaque {
+                        next_start = right_slope;
+                    } else {
+                        blocked = false;
+                        start = next_start;
+                    }
+                } else if opaque && distance < radius {
+                    blocked = true;
+                    self.cast_light(center, distance + 1, start, left_slope, radius, octant);
+                    next_start = right_slope;
+                }
+            }
 
-	for x in start.0..=end.0 {
-	    for y in start.1..=end.1 {
-		self.reveal(x, y);
-		self.light(x, y);
-	    }
-	}
+            if blocked {
+                break;
+            }
+        }
     }
 }
 
+/// Transformations mapping the first octant onto all eight octants.
+const OCTANTS: [(i32, i32, i32, i32); 8] = [
+    (1, 0, 0, 1),
+    (0, 1, 1, 0),
+    (0, -1, 1, 0),
+    (-1, 0, 0, 1),
+    (-1, 0, 0, -1),
+    (0, -1, -1, 0),
+    (0, 1, -1, 0),
+    (1, 0, 0, -1),
+];
+
 pub fn tile_to_str(tile: &Tile) -> &str {
     if tile.is_visible() {
-        match tile.tile_type {
+        match &tile.tile_type {
             TileType::Floor => match tile.is_lit() {
 		true => ".",
 		false => " "
@@ -198,6 +287,7 @@ pub fn tile_to_str(tile: &Tile) -> &str {
             TileType::StairsUp => "<",
             TileType::Player => "@",
             TileType::Character(t) => t,
+            TileType::Item(t) => t,
 	    TileType::Door => match tile.is_open() {
 		true => "'",
 		false => "+",
@@ -298,4 +388,97 @@ mod tests {
 	assert_eq!(grid.tile_at(3, 4).is_visible(), false);
 	assert_eq!(grid.tile_at(4, 4).is_visible(), false);
     }
+
+    /// Build a grid made of floor, with walls drawn wherever `layout` has a `#`
+    /// and closed doors wherever it has a `+`.
+    fn grid_from(layout: &[&str]) -> TileGrid {
+        let mut grid = TileGrid::new(layout[0].len(), layout.len());
+        for (y, line) in layout.iter().enumerate() {
+            for (x, chr) in line.chars().enumerate() {
+                let tile_type = match chr {
+                    '#' => TileType::Wall,
+                    '+' => TileType::Door,
+                    _ => TileType::Floor,
+                };
+                grid.set_tile(x, y, Tile::from(tile_type));
+            }
+        }
+        grid
+    }
+
+    #[test]
+    fn test_clear_fog_of_war_stops_at_walls() {
+        let mut grid = grid_from(&[".......", ".......", "...#...", ".......", "......."]);
+
+        grid.clear_fog_of_war(&(0, 2), 10);
+
+        assert!(grid.tile_at(2, 2).is_visible());
+        assert!(grid.tile_at(3, 2).is_visible());
+        assert!(!grid.tile_at(4, 2).is_visible());
+        assert!(!grid.tile_at(6, 2).is_visible());
+        assert!(grid.tile_at(6, 0).is_visible());
+        assert!(grid.tile_at(6, 4).is_visible());
+    }
+
+    #[test]
+    fn test_clear_fog_of_war_hides_other_rooms() {
+        let mut grid = grid_from(&["...#...", "...#...", "...#..."]);
+
+        grid.clear_fog_of_war(&(1, 1), 10);
+
+        for y in 0..3 {
+            for x in 0..4 {
+                assert!(grid.tile_at(x, y).is_visible());
+                assert!(grid.tile_at(x, y).is_lit());
+            }
+            for x in 4..7 {
+                assert!(!grid.tile_at(x, y).is_visible());
+                assert!(!grid.tile_at(x, y).is_lit());
+            }
+        }
+    }
+
+    #[test]
+    fn test_clear_fog_of_war_sees_through_open_doors_only() {
+        let mut grid = grid_from(&["#####", "..+..", "#####"]);
+
+        grid.clear_fog_of_war(&(0, 1), 10);
+        assert!(grid.tile_at(2, 1).is_visible());
+        assert!(!grid.tile_at(3, 1).is_visible());
+
+        grid.tile_at_mut(2, 1).open();
+        grid.clear_fog_of_war(&(0, 1), 10);
+        assert!(grid.tile_at(3, 1).is_visible());
+        assert!(grid.tile_at(4, 1).is_visible());
+    }
+
+    #[test]
+    fn test_clear_fog_of_war_respects_radius() {
+        let mut grid = grid_from(&["........."]);
+
+        grid.clear_fog_of_war(&(0, 0), 3);
+
+        assert!(grid.tile_at(3, 0).is_lit());
+        assert!(!grid.tile_at(4, 0).is_visible());
+
+        // Moving away keeps explored tiles visible but unlit.
+        grid.clear_fog_of_war(&(8, 0), 3);
+        assert!(grid.tile_at(0, 0).is_visible());
+        assert!(!grid.tile_at(0, 0).is_lit());
+        assert!(grid.tile_at(5, 0).is_lit());
+    }
+
+    #[test]
+    fn test_reveal_all_explores_without_lighting() {
+        let mut grid = grid_from(&["...#...", "...#..."]);
+
+        grid.reveal_all();
+
+        for y in 0..2 {
+            for x in 0..7 {
+                assert!(grid.tile_at(x, y).is_visible());
+                assert!(!grid.tile_at(x, y).is_lit());
+            }
+        }
+    }
 }
@@ -1,33 +1,313 @@
 use crate::events::ViewportEvent;
-use crate::world::{DOWN, LEFT, RIGHT, UP};
+use crate::world::{apply_movement, Point};
 use crossterm::cursor;
 use crossterm::cursor::MoveTo;
-use crossterm::input::{input, InputEvent, KeyEvent, TerminalInput};
+use crossterm::input::{input, AsyncReader, InputEvent, KeyEvent, TerminalInput};
 use crossterm::screen::{EnterAlternateScreen, LeaveAlternateScreen, RawScreen};
-use crossterm::terminal;
+use crossterm::terminal::{self, Clear, ClearType};
 use crossterm::{execute, queue, Output};
 use log::debug;
+use std::cmp::min;
+use std::collections::VecDeque;
 use std::io::{stdout, Write};
+use std::iter;
+use std::thread;
+use std::time::Duration;
 
+use crate::bestiary;
+use crate::classes::Class;
 use crate::entities::{Entity, Player};
-use crate::state::State;
-use crate::tiling::tile_to_str;
+use crate::items::{self, Effect, Item, Slot};
+use crate::keymap::{Command, Key, Keymap, Screen};
+use crate::scores::{self, Score};
+use crate::state::{Destination, State};
+use crate::tiling::{tile_to_str, Tile, TileType};
 
 pub trait ViewPort {
     fn render_state(&mut self, state: &State);
     fn wait_input(&mut self) -> Option<ViewportEvent>;
+    /// Show a message to the player
+    fn notify(&mut self, message: String);
+    /// Show what the player carries and wears
+    fn show_inventory(&mut self, state: &State);
+    /// Show every message logged so far
+    fn show_history(&mut self, state: &State);
+    /// Tell the player they died and show the high `scores`, `rank` being
+    /// theirs if they made it. Asks whether to play again, answers with
+    /// `NewGame` or `Quit`
+    fn game_over(&mut self, state: &State, scores: &[Score], rank: Option<usize>) -> ViewportEvent;
+    /// Let the player pick one of `classes` for a new game, `None` if they
+    /// would rather quit
+    fn select_class(&mut self, classes: &[Class]) -> Option<usize>;
+    /// Show every command with its keys and what the map symbols mean
+    fn show_help(&mut self);
+    /// Let the player move a cursor over the level and tell them what is
+    /// under it
+    fn look(&mut self, state: &State);
+    /// Whether the player pressed a key to stop a walk in progress
+    fn interrupted(&mut self) -> bool {
+        false
+    }
+}
+
+/// Lines of the game over screen, with the keys of `keymap` to answer it.
+fn game_over_lines(
+    state: &State,
+    scores: &[Score],
+    rank: Option<usize>,
+    keymap: &Keymap,
+) -> Vec<String> {
+    let player = state.get_player();
+    let mut lines = vec![
+        String::from("You died!"),
+        String::new(),
+        format!(
+            "{} the {}, level {}",
+            player.name, player.class, player.level
+        ),
+        state
+            .cause_of_death()
+            .unwrap_or("Killed by something")
+            .to_string(),
+        format!("Died on level {}", state.depth()),
+        format!("Turns: {}  Kills: {}", state.turns(), state.kills()),
+        String::new(),
+        String::from("High scores:"),
+    ];
+    lines.extend(scores::table_lines(scores, rank));
+    lines.push(String::new());
+    lines.extend(state.messages().recent(MESSAGE_LINES).iter().cloned());
+    lines.push(String::new());
+    lines.push(format!(
+        "{}) new game  {}) quit",
+        keymap.hint(Command::NewGame),
+        keymap.hint(Command::Quit)
+    ));
+    lines
+}
+
+/// Lay `entries` out in as many columns as fit in `width` characters.
+fn columns(entries: &[String], width: usize) -> Vec<String> {
+    let column_width = entries.iter().map(|e| e.chars().count()).max().unwrap_or(0) + 2;
+    let per_line = (width / column_width).max(1);
+    entries
+        .chunks(per_line)
+        .map(|chunk| {
+            let line: String = chunk
+                .iter()
+                .map(|entry| format!("{:<1$}", entry, column_width))
+                .collect();
+            line.trim_end().to_string()
+        })
+        .collect()
+}
+
+/// Lines listing `commands` with their keys in `keymap`, fitting in `width`
+/// characters.
+fn command_lines(commands: &[Command], keymap: &Keymap, width: usize) -> Vec<String> {
+    let keys: Vec<String> = commands
+        .iter()
+        .map(|command| {
+            let keys: Vec<String> = keymap.keys(*command).iter().map(Key::to_string).collect();
+            keys.join(" ")
+        })
+        .collect();
+    let keys_width = keys.iter().map(|k| k.chars().count()).max().unwrap_or(0);
+    let entries: Vec<String> = commands
+        .iter()
+        .zip(keys.iter())
+        .map(|(command, keys)| format!("{:<2$} {}", keys, command.description(), keys_width))
+        .collect();
+    columns(&entries, width)
+}
+
+/// Pages of the help screen, listing the keys of `keymap` and the map
+/// symbols, fitting in `width` characters and `height` lines. Sections are
+/// only split across pages if they don't fit on one.
+pub fn help_pages(keymap: &Keymap, width: usize, height: usize) -> Vec<Vec<String>> {
+    let (level, prompts): (Vec<Command>, Vec<Command>) = Command::ALL
+        .iter()
+        .partition(|command| command.screens().contains(&Screen::Level));
+
+    let seen = |tile_type| Tile::new(tile_type, true, false, false, true);
+    let mut open_door = seen(TileType::Door);
+    open_door.open();
+    let items = [
+        (Item::equipment("", Slot::Weapon, 0, 0, 0), "weapon"),
+        (Item::equipment("", Slot::Armor, 0, 0, 0), "armor"),
+        (Item::equipment("", Slot::Accessory, 0, 0, 0), "accessory"),
+        (Item::potion("", Effect::Heal(0)), "potion"),
+        (Item::scroll("", Effect::Teleport), "scroll"),
+    ];
+    let mut tiles = vec![
+        (seen(TileType::Player), "you"),
+        (seen(TileType::Wall), "wall"),
+        (seen(TileType::Floor), "floor"),
+        (seen(TileType::Door), "closed door"),
+        (open_door, "open door"),
+        (seen(TileType::StairsUp), "stairs up"),
+        (seen(TileType::StairsDown), "stairs down"),
+    ];
+    for (item, name) in items.iter() {
+        tiles.push((seen(TileType::Item(item.glyph().to_string())), name));
+    }
+    let symbols: Vec<String> = tiles
+        .iter()
+        .map(|(tile, name)| format!("{} {}", tile_to_str(tile), name))
+        .collect();
+    let monsters: Vec<String> = bestiary::all()
+        .iter()
+        .map(|monster| format!("{} {}", monster.glyph, monster.name))
+        .collect();
+
+    let section = |title: &str, lines: Vec<String>| -> Vec<String> {
+        iter::once(String::from(title)).chain(lines).collect()
+    };
+    let sections = vec![
+        section("Commands", command_lines(&level, keymap, width)),
+        section("Prompts", command_lines(&prompts, keymap, width)),
+        section("Map symbols", columns(&symbols, width)),
+        section("Monsters", columns(&monsters, width)),
+    ];
+
+    let mut pages: Vec<Vec<String>> = vec![];
+    for section in sections {
+        match pages.last_mut() {
+            Some(page) if page.len() + 1 + section.len() <= height => {
+                page.push(String::new());
+                page.extend(section);
+            }
+            _ => pages.extend(section.chunks(height.max(1)).map(<[String]>::to_vec)),
+        }
+    }
+    pages
+}
+
+/// Status line under the level, cut or padded to `width`. The seed comes
+/// first so it is never cut off.
+pub fn status_line(state: &State, width: usize) -> String {
+    let status = format!("Seed {} | {}", state.seed(), state.get_player().stats());
+    format!("{:width$}", status, width = width)
+        .chars()
+        .take(width)
+        .collect()
+}
+
+/// How close to the edge of the view the player can get before it scrolls.
+const SCROLL_MARGIN: usize = 5;
+
+/// How long each step of a walk stays on screen.
+const WALK_DELAY: Duration = Duration::from_millis(20);
+
+/// How many of the last messages are shown under the level.
+pub const MESSAGE_LINES: usize = 3;
+
+/// Window of `xsize` by `ysize` tiles onto the level, starting at `start`.
+pub struct Camera {
+    start: Point,
+    xsize: usize,
+    ysize: usize,
+}
+
+impl Camera {
+    pub fn new(xsize: usize, ysize: usize) -> Camera {
+        Camera {
+            start: (0, 0),
+            xsize,
+            ysize,
+        }
+    }
+
+    pub fn start(&self) -> Point {
+        self.start
+    }
+
+    /// Scroll along one axis so `pos` stays away from the edges of the view,
+    /// without going past the end of the level.
+    fn scroll(start: usize, pos: usize, view: usize, level: usize) -> usize {
+        if level <= view {
+            return 0;
+        }
+
+        let margin = min(SCROLL_MARGIN, view.saturating_sub(1) / 2);
+        let start = if pos < start + margin {
+            pos.saturating_sub(margin)
+        } else if pos + margin >= start + view {
+            pos + margin + 1 - view
+        } else {
+            start
+        };
+        min(start, level - view)
+    }
+
+    /// Move the view to keep `target` in sight on a level of size `level`.
+    pub fn follow(&mut self, target: Point, level: (usize, usize)) {
+        self.start = (
+            Camera::scroll(self.start.0, target.0, self.xsize, level.0),
+            Camera::scroll(self.start.1, target.1, self.ysize, level.1),
+        );
+    }
+
+    /// Screen position of a level point, if it is in view.
+    pub fn to_screen(&self, point: Point) -> Option<Point> {
+        if point.0 < self.start.0
+            || point.1 < self.start.1
+            || point.0 >= self.start.0 + self.xsize
+            || point.1 >= self.start.1 + self.ysize
+        {
+            return None;
+        }
+        Some((point.0 - self.start.0, point.1 - self.start.1))
+    }
+
+    /// Follow the player of `state` and render the visible part of each line
+    /// of the level, padded to the width of the view.
+    fn level_lines(&mut self, state: &State) -> Vec<String> {
+        let grid = match state.get_grid() {
+            Some(g) => g,
+            None => return vec![],
+        };
+        self.follow(*state.get_player().location(), (grid.xsize(), grid.ysize()));
+
+        (0..self.ysize)
+            .map(|y| {
+                let mut line: String = match grid.raw_data().get(self.start.1 + y) {
+                    Some(row) => row
+                        .iter()
+                        .skip(self.start.0)
+                        .take(self.xsize)
+                        .map(tile_to_str)
+                        .collect(),
+                    None => String::new(),
+                };
+                let width = line.chars().count();
+                line.push_str(&" ".repeat(self.xsize.saturating_sub(width)));
+                line
+            })
+            .collect()
+    }
 }
 
 pub struct CrossTermViewPort {
     xsize: usize,
     ysize: usize,
+    /// Keeps the terminal in raw mode until the viewport is dropped
+    #[allow(dead_code)]
     raw: RawScreen,
     input: TerminalInput,
-    start: (usize, usize),
+    camera: Camera,
+    inventory: Vec<String>,
+    keymap: Keymap,
+    /// Where the player stands, and the size of their level, as last rendered
+    player: Point,
+    level_size: (usize, usize),
+    /// Keys pressed during a walk, read without waiting
+    walk_input: Option<AsyncReader>,
 }
 
 impl CrossTermViewPort {
-    pub fn new() -> CrossTermViewPort {
+    /// Take over the terminal, reading keys through `keymap`.
+    pub fn new(keymap: Keymap) -> CrossTermViewPort {
         // Initialise the terminal, the raw alternate mode allows direct character
         // seeking and hides the prompt.
         let term_size = terminal::size().unwrap();
@@ -37,7 +317,7 @@ impl CrossTermViewPort {
 
         // Initialise state, create the player and dungeon
         let xsize = term_size.0 as usize;
-        let ysize = (term_size.1 - 2) as usize;
+        let ysize = (term_size.1 as usize).saturating_sub(1 + MESSAGE_LINES);
 
         let input = input();
 
@@ -46,61 +326,213 @@ impl CrossTermViewPort {
             ysize,
             raw,
             input,
-            start: (0, 0),
+            camera: Camera::new(xsize, ysize),
+            inventory: vec![],
+            keymap,
+            player: (0, 0),
+            level_size: (0, 0),
+            walk_input: None,
+        }
+    }
+
+    /// Wait for the next key press.
+    fn read_key(&mut self) -> Option<KeyEvent> {
+        // A walk is over once the game waits for a key again.
+        self.walk_input = None;
+        let mut reader = self.input.read_sync();
+        match reader.next() {
+            Some(InputEvent::Keyboard(key)) => Some(key),
+            _ => None,
         }
     }
 
-    fn draw_level(&self, state: &State) {
+    /// Wait for the next key press and translate it for `screen`. `None` if
+    /// there is no more input, `Some(None)` for keys doing nothing there.
+    fn read_command(&mut self, screen: Screen) -> Option<Option<Command>> {
+        let key = self.read_key()?;
+        Some(to_key(key).and_then(|key| self.keymap.command(screen, key)))
+    }
+
+    /// Ask where to travel, moving a cursor over the level with the movement
+    /// keys. The stairs can be picked directly.
+    fn prompt_destination(&mut self) -> Option<Destination> {
+        self.notify(format!(
+            "Travel to? Move the cursor, {} to go, {} or {} for stairs, {} to cancel",
+            self.keymap.hint(Command::Confirm),
+            self.keymap.hint(Command::DownStairs),
+            self.keymap.hint(Command::UpStairs),
+            self.keymap.hint(Command::Cancel)
+        ));
+        let mut cursor = self.player;
+        let destination = loop {
+            self.show_cursor(cursor);
+            match self.read_command(Screen::Travel) {
+                Some(Some(Command::Cancel)) | None => break None,
+                Some(Some(Command::DownStairs)) => break Some(Destination::DownStairs),
+                Some(Some(Command::UpStairs)) => break Some(Destination::UpStairs),
+                Some(Some(Command::Confirm)) => break Some(Destination::Location(cursor)),
+                Some(Some(command)) => cursor = self.move_cursor(cursor, command),
+                Some(None) => (),
+            }
+        };
+        execute!(stdout(), cursor::Hide).unwrap();
+        destination
+    }
+
+    /// Show the terminal cursor over the level point `cursor`.
+    fn show_cursor(&self, cursor: Point) {
+        if let Some(screen) = self.camera.to_screen(cursor) {
+            execute!(
+                stdout(),
+                MoveTo(screen.0 as u16, screen.1 as u16),
+                cursor::Show
+            )
+            .unwrap();
+        }
+    }
+
+    /// Where `cursor` goes for `command`. Movement commands move it, within
+    /// the level and the view.
+    fn move_cursor(&self, cursor: Point, command: Command) -> Point {
+        let next = command
+            .movement()
+            .and_then(|movement| apply_movement(cursor, movement).ok());
+        match next {
+            Some(next)
+                if next.0 < self.level_size.0
+                    && next.1 < self.level_size.1
+                    && self.camera.to_screen(next).is_some() =>
+            {
+                next
+            }
+            _ => cursor,
+        }
+    }
+
+    /// Ask for an inventory item with `question`, returns its index.
+    fn prompt_item(&mut self, question: &str) -> Option<usize> {
+        if self.inventory.is_empty() {
+            self.notify(String::from("You aren't carrying anything."));
+            return None;
+        }
+        let choices: Vec<String> = self
+            .inventory
+            .iter()
+            .enumerate()
+            .map(|(i, name)| format!("{}) {}", items::letter(i), name))
+            .collect();
+        self.notify(format!("{} {}", question, choices.join(" ")));
+
+        match self.read_key() {
+            Some(KeyEvent::Char(chr)) => {
+                items::index_of(chr).filter(|index| *index < self.inventory.len())
+            }
+            _ => None,
+        }
+    }
+
+    /// Draw `lines` on a screen of their own.
+    fn draw_page(&self, lines: &[String]) {
+        let mut sout = stdout();
+        queue!(sout, Clear(ClearType::All)).unwrap();
+        for (linenum, line) in lines.iter().enumerate() {
+            queue!(sout, MoveTo(0, linenum as u16), Output(line.clone())).unwrap();
+        }
+        sout.flush().unwrap();
+    }
+
+    /// Show `lines` on a screen of their own until a key is pressed.
+    fn show_page(&mut self, lines: &[String]) {
+        let mut page = lines.to_vec();
+        page.push(String::new());
+        page.push(String::from("Press any key to continue."));
+        self.draw_page(&page);
+
+        self.read_key();
+        execute!(stdout(), Clear(ClearType::All)).unwrap();
+    }
+
+    /// Ask which slot to take off.
+    fn prompt_unequip(&mut self) -> Option<ViewportEvent> {
+        self.notify(format!(
+            "Take off what? {}) weapon {}) armor {}) accessory",
+            self.keymap.hint(Command::TakeOffWeapon),
+            self.keymap.hint(Command::TakeOffArmor),
+            self.keymap.hint(Command::TakeOffAccessory)
+        ));
+        match self.read_command(Screen::TakeOff)? {
+            Some(Command::TakeOffWeapon) => Some(ViewportEvent::Unequip(Slot::Weapon)),
+            Some(Command::TakeOffArmor) => Some(ViewportEvent::Unequip(Slot::Armor)),
+            Some(Command::TakeOffAccessory) => Some(ViewportEvent::Unequip(Slot::Accessory)),
+            _ => None,
+        }
+    }
+
+    fn draw_level(&mut self, state: &State) {
         let mut sout = stdout();
-        let grid = state.get_grid().unwrap();
-        execute!(sout, MoveTo(0, 0)).unwrap();
-        for (linenum, line) in grid.raw_data().iter().enumerate() {
+        for (linenum, line) in self.camera.level_lines(state).into_iter().enumerate() {
             debug!("Drawing linenum {} -- {:?}", linenum, line);
-            let linestr = line.iter().map(tile_to_str).collect::<Vec<&str>>();
-            let mut linestr2 = String::from("");
-            for chr in linestr {
-                linestr2.push_str(chr);
-            }
-            queue!(sout, Output(linestr2), MoveTo(0, linenum as u16)).unwrap();
-            sout.flush().unwrap();
+            queue!(sout, MoveTo(0, linenum as u16), Output(line)).unwrap();
         }
+        sout.flush().unwrap();
     }
 
-    fn draw_entity(&self, state: &State, entity: &dyn Entity) {
+    fn draw_entity(&self, entity: &dyn Entity) {
         if !entity.is_visible() || !entity.is_dirty() {
             return;
         }
-        let grid = state.get_grid().unwrap();
-        let dirt = entity.previous_location();
-        let background = grid.block_at(dirt.0, dirt.1);
+        // No need to clean up behind the entity, the whole level was just
+        // redrawn.
         let mut sout = stdout();
-        queue!(
-            sout,
-            MoveTo(dirt.0 as u16, dirt.1 as u16),
-            Output(tile_to_str(background)),
-            MoveTo(entity.location().0 as u16, entity.location().1 as u16),
-            Output(tile_to_str(entity.tile()))
-        )
-        .unwrap();
+        if let Some(screen) = self.camera.to_screen(*entity.location()) {
+            queue!(
+                sout,
+                MoveTo(screen.0 as u16, screen.1 as u16),
+                Output(tile_to_str(entity.tile()))
+            )
+            .unwrap();
+        }
         sout.flush().unwrap();
     }
 
     fn draw_entities(&self, state: &State) {
-        for e in state.current_level().entities.iter() {
-            self.draw_entity(state, &**e);
+        for e in state.floor_first() {
+            self.draw_entity(e);
         }
     }
 
     fn draw_player(&mut self, state: &State) {
-        self.draw_entity(state, state.get_player());
+        self.draw_entity(state.get_player());
     }
 
     fn ui_state_position(&self) -> MoveTo {
         MoveTo(0, (self.ysize) as u16)
     }
 
+    fn ui_message_position(&self, line: usize) -> MoveTo {
+        MoveTo(0, (self.ysize + 1 + line) as u16)
+    }
+
+    /// Prompts go on the last line, messages are drawn back on the next render.
     fn ui_notification_position(&self) -> MoveTo {
-        MoveTo(0, (self.ysize + 1) as u16)
+        self.ui_message_position(MESSAGE_LINES - 1)
+    }
+
+    fn draw_messages(&self, state: &State) {
+        let mut sout = stdout();
+        let recent = state.messages().recent(MESSAGE_LINES);
+        for line in 0..MESSAGE_LINES {
+            let message = recent.get(line).map(String::as_str).unwrap_or("");
+            queue!(
+                sout,
+                self.ui_message_position(line),
+                Output(" ".repeat(self.xsize)),
+                self.ui_message_position(line),
+                Output(message.chars().take(self.xsize).collect::<String>())
+            )
+            .unwrap();
+        }
+        sout.flush().unwrap();
     }
 
     fn draw_ui(&self, state: &State) {
@@ -108,13 +540,163 @@ impl CrossTermViewPort {
         queue!(
             sout,
             self.ui_state_position(),
-            Output(state.get_player().stats())
+            Output(status_line(state, self.xsize))
         )
         .unwrap();
         sout.flush().unwrap();
     }
+}
+
+/// Key the keymap can hold for a key press, if any.
+fn to_key(key: KeyEvent) -> Option<Key> {
+    match key {
+        KeyEvent::Char(c) => Some(Key::Char(c)),
+        KeyEvent::Ctrl(c) => Some(Key::Ctrl(c)),
+        KeyEvent::Up => Some(Key::Up),
+        KeyEvent::Down => Some(Key::Down),
+        KeyEvent::Left => Some(Key::Left),
+        KeyEvent::Right => Some(Key::Right),
+        KeyEvent::Home => Some(Key::Home),
+        KeyEvent::End => Some(Key::End),
+        KeyEvent::PageUp => Some(Key::PageUp),
+        KeyEvent::PageDown => Some(Key::PageDown),
+        KeyEvent::Enter => Some(Key::Enter),
+        KeyEvent::Esc => Some(Key::Esc),
+        KeyEvent::Tab => Some(Key::Tab),
+        KeyEvent::BackTab => Some(Key::BackTab),
+        _ => None,
+    }
+}
+
+impl Default for CrossTermViewPort {
+    fn default() -> Self {
+        Self::new(Keymap::default())
+    }
+}
+
+impl ViewPort for CrossTermViewPort {
+    fn render_state(&mut self, state: &State) {
+        self.player = *state.get_player().location();
+        if let Some(grid) = state.get_grid() {
+            self.level_size = (grid.xsize(), grid.ysize());
+        }
+        self.inventory = state
+            .get_player()
+            .inventory()
+            .iter()
+            .map(|item| item.to_string())
+            .collect();
+        self.draw_level(state);
+        self.draw_entities(state);
+        self.draw_player(state);
+        self.draw_ui(state);
+        self.draw_messages(state);
+    }
+
+    fn show_inventory(&mut self, state: &State) {
+        let player = state.get_player();
+        self.show_page(&items::inventory_lines(
+            player.inventory(),
+            player.equipment(),
+        ));
+    }
 
-    pub fn notify(&self, message: String) {
+    fn show_help(&mut self) {
+        // Keep two lines for the key reminder of the pages.
+        let height = self.ysize + MESSAGE_LINES - 1;
+        for page in help_pages(&self.keymap, self.xsize, height) {
+            self.show_page(&page);
+        }
+    }
+
+    fn show_history(&mut self, state: &State) {
+        let lines = state.messages().lines();
+        // Keep the last line of the screen for the key reminder.
+        let height = self.ysize + MESSAGE_LINES;
+        let last_top = lines.len().saturating_sub(height);
+        let mut top = last_top;
+
+        loop {
+            let mut page: Vec<String> = lines
+                .iter()
+                .skip(top)
+                .take(height)
+                .map(|line| line.chars().take(self.xsize).collect())
+                .collect();
+            if lines.is_empty() {
+                page.push(String::from("No messages yet."));
+            }
+            page.resize(height, String::new());
+            page.push(format!(
+                "Messages - scroll: {}, {}, {}, {}, close: {}/{}",
+                self.keymap.hint(Command::ScrollUp),
+                self.keymap.hint(Command::ScrollDown),
+                self.keymap.hint(Command::ScrollPageUp),
+                self.keymap.hint(Command::ScrollPageDown),
+                self.keymap.hint(Command::Quit),
+                self.keymap.hint(Command::Cancel)
+            ));
+            self.draw_page(&page);
+
+            match self.read_command(Screen::History) {
+                Some(Some(Command::ScrollUp)) => top = top.saturating_sub(1),
+                Some(Some(Command::ScrollDown)) => top = min(top + 1, last_top),
+                Some(Some(Command::ScrollPageUp)) => top = top.saturating_sub(height),
+                Some(Some(Command::ScrollPageDown)) => top = min(top + height, last_top),
+                Some(Some(Command::Quit)) | Some(Some(Command::Cancel)) | None => break,
+                _ => (),
+            }
+        }
+        execute!(stdout(), Clear(ClearType::All)).unwrap();
+    }
+
+    fn game_over(&mut self, state: &State, scores: &[Score], rank: Option<usize>) -> ViewportEvent {
+        self.draw_page(&game_over_lines(state, scores, rank, &self.keymap));
+        let choice = loop {
+            match self.read_command(Screen::GameOver) {
+                Some(Some(Command::NewGame)) => break ViewportEvent::NewGame,
+                Some(Some(Command::Quit)) | Some(Some(Command::Cancel)) => {
+                    break ViewportEvent::Quit
+                }
+                _ => continue,
+            }
+        };
+        execute!(stdout(), Clear(ClearType::All)).unwrap();
+        choice
+    }
+
+    fn select_class(&mut self, classes: &[Class]) -> Option<usize> {
+        let mut page = vec![String::from("Choose your class:"), String::new()];
+        for (i, class) in classes.iter().enumerate() {
+            page.push(format!("{}) {}", items::letter(i), class.summary()));
+        }
+        page.push(String::new());
+        page.push(format!(
+            "Press a letter to choose, {} to quit.",
+            self.keymap.hint(Command::Cancel)
+        ));
+        self.draw_page(&page);
+
+        let choice = loop {
+            let key = match self.read_key() {
+                Some(key) => key,
+                None => continue,
+            };
+            let command = to_key(key).and_then(|key| self.keymap.command(Screen::Classes, key));
+            match key {
+                _ if command == Some(Command::Cancel) => break None,
+                KeyEvent::Char(chr) => match items::index_of(chr) {
+                    Some(index) if index < classes.len() => break Some(index),
+                    _ => continue,
+                },
+                _ => continue,
+            }
+        };
+        execute!(stdout(), Clear(ClearType::All)).unwrap();
+        choice
+    }
+
+    fn notify(&mut self, message: String) {
         let mut sout = stdout();
         queue!(
             sout,
@@ -127,50 +709,100 @@ impl CrossTermViewPort {
         sout.flush().unwrap();
     }
 
-    pub fn ui_help(&self) {
-        self.notify(String::from(
-            "quit: q, movement{up(k), down(j), left(h), right(l)}",
-        ))
+    fn look(&mut self, state: &State) {
+        let hint = format!(
+            "Look - move the cursor, {} for the next monster, {} to stop",
+            self.keymap.hint(Command::NextTarget),
+            self.keymap.hint(Command::Cancel)
+        );
+        let mut sout = stdout();
+        queue!(
+            sout,
+            self.ui_message_position(MESSAGE_LINES - 2),
+            Output(" ".repeat(self.xsize)),
+            self.ui_message_position(MESSAGE_LINES - 2),
+            Output(hint)
+        )
+        .unwrap();
+
+        let monsters = state.visible_monsters();
+        let mut monster = None;
+        let mut cursor = *state.get_player().location();
+        loop {
+            self.notify(state.describe(cursor).chars().take(self.xsize).collect());
+            self.show_cursor(cursor);
+            let command = match self.read_command(Screen::Look) {
+                Some(Some(Command::Cancel)) | None => break,
+                Some(Some(command)) => command,
+                Some(None) => continue,
+            };
+            let next = match command {
+                Command::NextTarget | Command::PreviousTarget if monsters.is_empty() => continue,
+                Command::NextTarget => monster.map_or(0, |i| (i + 1) % monsters.len()),
+                Command::PreviousTarget => match monster {
+                    Some(i) if i > 0 => i - 1,
+                    _ => monsters.len() - 1,
+                },
+                _ => {
+                    cursor = self.move_cursor(cursor, command);
+                    continue;
+                }
+            };
+            monster = Some(next);
+            cursor = monsters[next];
+        }
+        execute!(stdout(), cursor::Hide).unwrap();
     }
-}
 
-impl ViewPort for CrossTermViewPort {
-    fn render_state(&mut self, state: &State) {
-        self.draw_level(state);
-        self.draw_entities(state);
-        self.draw_player(state);
-        self.draw_ui(state);
+    fn interrupted(&mut self) -> bool {
+        thread::sleep(WALK_DELAY);
+        let input = &self.input;
+        self.walk_input
+            .get_or_insert_with(|| input.read_async())
+            .next()
+            .is_some()
     }
 
     fn wait_input(&mut self) -> Option<ViewportEvent> {
-        let mut reader = self.input.read_sync();
+        let command = self.read_command(Screen::Level)??;
+        match command {
+            Command::Quit => Some(ViewportEvent::Quit),
+            Command::SaveQuit => Some(ViewportEvent::SaveQuit),
+            Command::Help => Some(ViewportEvent::Help),
+            Command::MoveUp
+            | Command::MoveDown
+            | Command::MoveLeft
+            | Command::MoveRight
+            | Command::MoveUpLeft
+            | Command::MoveUpRight
+            | Command::MoveDownLeft
+            | Command::MoveDownRight => command.movement().map(ViewportEvent::MovePlayer),
 
-        if let Some(event) = reader.next() {
-            return match event {
-                InputEvent::Keyboard(KeyEvent::Char('q')) => Some(ViewportEvent::Quit),
-                InputEvent::Keyboard(KeyEvent::Char('?')) => {
-                    self.ui_help();
-                    None
-                }
-                InputEvent::Keyboard(KeyEvent::Char('j')) => Some(ViewportEvent::MovePlayer(DOWN)),
-                InputEvent::Keyboard(KeyEvent::Char('k')) => Some(ViewportEvent::MovePlayer(UP)),
-                InputEvent::Keyboard(KeyEvent::Char('h')) => Some(ViewportEvent::MovePlayer(LEFT)),
-                InputEvent::Keyboard(KeyEvent::Char('l')) => Some(ViewportEvent::MovePlayer(RIGHT)),
-                // Arrow keys for noobs
-                InputEvent::Keyboard(KeyEvent::Down) => Some(ViewportEvent::MovePlayer(DOWN)),
-                InputEvent::Keyboard(KeyEvent::Up) => Some(ViewportEvent::MovePlayer(UP)),
-                InputEvent::Keyboard(KeyEvent::Left) => Some(ViewportEvent::MovePlayer(LEFT)),
-                InputEvent::Keyboard(KeyEvent::Right) => Some(ViewportEvent::MovePlayer(RIGHT)),
-
-                // Stairs
-                InputEvent::Keyboard(KeyEvent::Char('>')) => Some(ViewportEvent::DownStairs),
-                InputEvent::Keyboard(KeyEvent::Char('<')) => Some(ViewportEvent::UpStairs),
-
-                // No match
-                _ => None,
-            };
+            // Stairs
+            Command::DownStairs => Some(ViewportEvent::DownStairs),
+            Command::UpStairs => Some(ViewportEvent::UpStairs),
+            Command::Explore => Some(ViewportEvent::Explore),
+            Command::Travel => self.prompt_destination().map(ViewportEvent::Travel),
+            Command::Look => Some(ViewportEvent::Look),
+
+            // Equipment
+            Command::Wear => self.prompt_item("Wear what?").map(ViewportEvent::Equip),
+            Command::TakeOff => self.prompt_unequip(),
+
+            // Inventory
+            Command::PickUp => Some(ViewportEvent::PickUp),
+            Command::Use => self
+                .prompt_item("Quaff or read what?")
+                .map(ViewportEvent::Use),
+            Command::Drop => self.prompt_item("Drop what?").map(ViewportEvent::Drop),
+            Command::Inventory => Some(ViewportEvent::Inventory),
+
+            // Messages
+            Command::History => Some(ViewportEvent::History),
+
+            // Prompt answers aren't bound on the level
+            _ => None,
         }
-        None
     }
 }
 
@@ -180,3 +812,229 @@ impl Drop for CrossTermViewPort {
         execute!(stdout(), cursor::Show).unwrap();
     }
 }
+
+/// Viewport rendering into an in-memory screen and playing a script of
+/// events, for tests and bots that don't have a terminal.
+pub struct HeadlessViewPort {
+    xsize: usize,
+    ysize: usize,
+    screen: Vec<Vec<char>>,
+    events: VecDeque<ViewportEvent>,
+    messages: Vec<String>,
+    pages: Vec<Vec<String>>,
+    camera: Camera,
+}
+
+impl HeadlessViewPort {
+    /// Create a screen of `xsize` by `ysize` characters for the level, plus
+    /// the status line and `MESSAGE_LINES` message lines, that will play
+    /// `events` in order.
+    pub fn new(xsize: usize, ysize: usize, events: Vec<ViewportEvent>) -> HeadlessViewPort {
+        HeadlessViewPort {
+            xsize,
+            ysize,
+            screen: vec![vec![' '; xsize]; ysize + 1 + MESSAGE_LINES],
+            events: events.into_iter().collect(),
+            messages: vec![],
+            pages: vec![],
+            camera: Camera::new(xsize, ysize),
+        }
+    }
+
+    /// Queue more events after the current script.
+    pub fn push_event(&mut self, event: ViewportEvent) {
+        self.events.push_back(event);
+    }
+
+    /// Every prompt and hint notified so far, oldest first. Game messages go
+    /// to the log of the state.
+    pub fn messages(&self) -> &[String] {
+        &self.messages
+    }
+
+    /// Every full screen page shown so far, such as the inventory, oldest
+    /// first.
+    pub fn pages(&self) -> &[Vec<String>] {
+        &self.pages
+    }
+
+    /// Character drawn at a given screen position.
+    pub fn char_at(&self, x: usize, y: usize) -> char {
+        self.screen[y][x]
+    }
+
+    /// The whole screen, one line per row.
+    pub fn screen(&self) -> String {
+        self.screen
+            .iter()
+            .map(|line| line.iter().collect::<String>())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    fn write(&mut self, x: usize, y: usize, text: &str) {
+        if y >= self.screen.len() {
+            return;
+        }
+        for (i, chr) in text.chars().enumerate() {
+            if x + i >= self.xsize {
+                break;
+            }
+            self.screen[y][x + i] = chr;
+        }
+    }
+
+    fn clear_line(&mut self, y: usize) {
+        self.screen[y] = vec![' '; self.xsize];
+    }
+
+    /// Where the camera looks from, in level coordinates.
+    pub fn camera_start(&self) -> Point {
+        self.camera.start()
+    }
+
+    fn draw_entity(&mut self, entity: &dyn Entity) {
+        if !entity.is_visible() {
+            return;
+        }
+        if let Some(screen) = self.camera.to_screen(*entity.location()) {
+            self.write(screen.0, screen.1, tile_to_str(entity.tile()));
+        }
+    }
+}
+
+impl ViewPort for HeadlessViewPort {
+    fn render_state(&mut self, state: &State) {
+        for y in 0..self.ysize {
+            self.clear_line(y);
+        }
+
+        for (y, line) in self.camera.level_lines(state).iter().enumerate() {
+            self.write(0, y, line);
+        }
+
+        for e in state.floor_first() {
+            self.draw_entity(e);
+        }
+        self.draw_entity(state.get_player());
+
+        self.clear_line(self.ysize);
+        self.write(0, self.ysize, &status_line(state, self.xsize));
+
+        let recent = state.messages().recent(MESSAGE_LINES);
+        for line in 0..MESSAGE_LINES {
+            self.clear_line(self.ysize + 1 + line);
+            if let Some(message) = recent.get(line) {
+                self.write(0, self.ysize + 1 + line, message);
+            }
+        }
+    }
+
+    fn wait_input(&mut self) -> Option<ViewportEvent> {
+        // Quit once the script is over so the main loop always ends.
+        Some(self.events.pop_front().unwrap_or(ViewportEvent::Quit))
+    }
+
+    fn show_inventory(&mut self, state: &State) {
+        let player = state.get_player();
+        self.pages.push(items::inventory_lines(
+            player.inventory(),
+            player.equipment(),
+        ));
+    }
+
+    /// Shows the default keys.
+    fn show_help(&mut self) {
+        let height = self.ysize + MESSAGE_LINES - 1;
+        self.pages
+            .extend(help_pages(&Keymap::default(), self.xsize, height));
+    }
+
+    fn show_history(&mut self, state: &State) {
+        self.pages.push(state.messages().lines().to_vec());
+    }
+
+    /// Looks at the player's spot, then at each monster in view as Tab
+    /// would.
+    fn look(&mut self, state: &State) {
+        let player = *state.get_player().location();
+        for location in iter::once(player).chain(state.visible_monsters()) {
+            self.notify(state.describe(location));
+        }
+    }
+
+    /// Answers with the next scripted event if it is `NewGame`, quits
+    /// otherwise.
+    fn game_over(&mut self, state: &State, scores: &[Score], rank: Option<usize>) -> ViewportEvent {
+        self.pages
+            .push(game_over_lines(state, scores, rank, &Keymap::default()));
+        match self.events.pop_front() {
+            Some(ViewportEvent::NewGame) => ViewportEvent::NewGame,
+            _ => ViewportEvent::Quit,
+        }
+    }
+
+    /// Always picks the first class.
+    fn select_class(&mut self, classes: &[Class]) -> Option<usize> {
+        if classes.is_empty() {
+            None
+        } else {
+            Some(0)
+        }
+    }
+
+    fn notify(&mut self, message: String) {
+        let line = self.ysize + MESSAGE_LINES;
+        self.clear_line(line);
+        self.write(0, line, &message);
+        self.messages.push(message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_camera_stays_still_on_small_levels() {
+        let mut camera = Camera::new(80, 22);
+        camera.follow((79, 21), (80, 22));
+        assert_eq!(camera.start(), (0, 0));
+    }
+
+    #[test]
+    fn test_camera_scrolls_near_the_edge() {
+        let mut camera = Camera::new(20, 10);
+
+        // Away from the edges, nothing moves.
+        camera.follow((10, 5), (100, 50));
+        assert_eq!(camera.start(), (0, 0));
+
+        // Getting close to the right and bottom edges scrolls.
+        camera.follow((16, 7), (100, 50));
+        assert_eq!(camera.start(), (2, 2));
+
+        // Going back scrolls the other way.
+        camera.follow((3, 3), (100, 50));
+        assert_eq!(camera.start(), (0, 0));
+    }
+
+    #[test]
+    fn test_camera_stops_at_the_end_of_the_level() {
+        let mut camera = Camera::new(20, 10);
+        camera.follow((99, 49), (100, 50));
+        assert_eq!(camera.start(), (80, 40));
+    }
+
+    #[test]
+    fn test_camera_clips_points_out_of_view() {
+        let mut camera = Camera::new(20, 10);
+        camera.follow((50, 25), (100, 50));
+        let start = camera.start();
+
+        assert_eq!(camera.to_screen(start), Some((0, 0)));
+        assert_eq!(camera.to_screen((start.0 + 19, start.1 + 9)), Some((19, 9)));
+        assert_eq!(camera.to_screen((start.0 + 20, start.1)), None);
+        assert_eq!(camera.to_screen((start.0 - 1, start.1)), None);
+    }
+}
@@ -1,13 +1,38 @@
-use crate::entities::{Character, Enemy, Entity};
+use crate::bestiary::SpawnTable;
+use crate::entities::{entity_list, Entity, FloorItem};
+use crate::items::Item;
 use crate::tiling::{Tile, TileGrid, TileType, Tileable};
-use rand::Rng;
-use std::cmp::{min, PartialEq};
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::cmp::{max, min, PartialEq};
 use std::fmt;
 
+/// Random generator used to build dungeons, portable so that a seed gives
+/// the same dungeon on every platform.
+pub type DungeonRng = rand_pcg::Pcg64;
+
+/// How many times to try placing a room before deciding the level is full.
+const ROOM_ATTEMPTS: usize = 1000;
+/// Smallest and largest sides of the rooms made by `random_room`.
+const MIN_ROOM_SIZE: usize = 4;
+const MAX_ROOM_SIZE: usize = 11;
+/// Space kept between rooms.
+const ROOM_PADDING: usize = 2;
+
+/// Smallest level width generation copes with, as at least two rooms must fit
+/// side by side whatever the place of the first one.
+pub const MIN_LEVEL_XSIZE: usize = 3 * MAX_ROOM_SIZE + 2 * ROOM_PADDING;
+/// Smallest level height generation copes with, as a room must fit.
+pub const MIN_LEVEL_YSIZE: usize = MAX_ROOM_SIZE + 1;
+/// Largest level sides, beyond that levels take too long to explore and save.
+pub const MAX_LEVEL_SIZE: usize = 250;
+/// Deepest dungeon that can be generated.
+pub const MAX_DEPTH: usize = 50;
+
 pub type Point = (usize, usize);
 pub type Movement = (i8, i8);
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Serialize, Deserialize)]
 enum CorridorType {
     Horizontal,
     Vertical,
@@ -17,6 +42,26 @@ pub const LEFT: Movement = (-1, 0);
 pub const RIGHT: Movement = (1, 0);
 pub const UP: Movement = (0, -1);
 pub const DOWN: Movement = (0, 1);
+pub const UP_LEFT: Movement = (-1, -1);
+pub const UP_RIGHT: Movement = (1, -1);
+pub const DOWN_LEFT: Movement = (-1, 1);
+pub const DOWN_RIGHT: Movement = (1, 1);
+/// Every step an actor can take, straight ones first.
+pub const DIRECTIONS: [Movement; 8] = [
+    LEFT, RIGHT, UP, DOWN, UP_LEFT, UP_RIGHT, DOWN_LEFT, DOWN_RIGHT,
+];
+
+pub fn is_diagonal(movement: Movement) -> bool {
+    movement.0 != 0 && movement.1 != 0
+}
+
+/// Single step from `from` heading towards `to`, diagonally if need be.
+pub fn direction(from: Point, to: Point) -> Movement {
+    (
+        (to.0 as i32 - from.0 as i32).signum() as i8,
+        (to.1 as i32 - from.1 as i32).signum() as i8,
+    )
+}
 
 pub fn apply_movement(point: Point, movement: Movement) -> Result<Point, String> {
     let x = point.0 as i32 + movement.0 as i32;
@@ -27,6 +72,21 @@ pub fn apply_movement(point: Point, movement: Movement) -> Result<Point, String>
     Ok((x as usize, y as usize))
 }
 
+/// Number of steps between two points, diagonal steps included.
+pub fn distance(a: Point, b: Point) -> usize {
+    let dx = (a.0 as i32 - b.0 as i32).abs();
+    let dy = (a.1 as i32 - b.1 as i32).abs();
+    dx.max(dy) as usize
+}
+
+/// Number of orthogonal steps between two points.
+pub fn manhattan_distance(a: Point, b: Point) -> usize {
+    let dx = (a.0 as i32 - b.0 as i32).abs();
+    let dy = (a.1 as i32 - b.1 as i32).abs();
+    (dx + dy) as usize
+}
+
+#[derive(Serialize, Deserialize)]
 struct Room {
     start: Point,
     center: Point,
@@ -46,6 +106,17 @@ impl Room {
             ),
         }
     }
+
+    /// Whether `point` is on one of the room's walls, corners excepted.
+    fn in_wall(&self, point: Point) -> bool {
+        let endx = self.start.0 + self.width;
+        let endy = self.start.1 + self.height;
+        let within_x = point.0 > self.start.0 && point.0 < endx;
+        let within_y = point.1 > self.start.1 && point.1 < endy;
+
+        ((point.0 == self.start.0 || point.0 == endx) && within_y)
+            || ((point.1 == self.start.1 || point.1 == endy) && within_x)
+    }
 }
 
 impl Tileable for Room {
@@ -79,7 +150,7 @@ impl Tileable for Room {
     }
 }
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Serialize, Deserialize)]
 struct Corridor {
     start: Point,
     length: usize,
@@ -154,11 +225,10 @@ impl Corridor {
         Ok(Corridor::new(origin, length, dir))
     }
 
-    pub fn link(start: Point, end: Point) -> Result<Vec<Corridor>, String> {
+    pub fn link<R: Rng>(start: Point, end: Point, rng: &mut R) -> Result<Vec<Corridor>, String> {
         if start.0 == end.0 || start.1 == end.1 {
             return Ok(vec![Corridor::make(start, end)?]);
         }
-        let mut rng = rand::thread_rng();
         let start_hor = rng.gen_bool(0.5);
         let angle_point = if start_hor {
             (end.0, start.1)
@@ -172,6 +242,29 @@ impl Corridor {
         ])
     }
 
+    /// The floor tiles dug for the corridor.
+    fn points(&self) -> Vec<Point> {
+        (0..self.length)
+            .map(|i| match self.direction {
+                CorridorType::Horizontal => (self.start.0 + i, self.start.1),
+                CorridorType::Vertical => (self.start.0, self.start.1 + i),
+            })
+            .collect()
+    }
+
+    /// Whether `point` on the corridor is walled on both sides, so a door
+    /// there closes the way through.
+    fn walled_at(&self, grid: &TileGrid, point: Point) -> bool {
+        let (x, y) = point;
+        let sides = match self.direction {
+            CorridorType::Horizontal => [(x, y - 1), (x, y + 1)],
+            CorridorType::Vertical => [(x - 1, y), (x + 1, y)],
+        };
+        sides
+            .iter()
+            .all(|side| *grid.tile_at(side.0, side.1).get_type() == TileType::Wall)
+    }
+
     fn tile_vertical(&self, grid: &mut TileGrid) {
         let x = self.start.0;
         let endy = self.start.1 + self.length;
@@ -188,12 +281,6 @@ impl Corridor {
         grid.set_empty_tile(x - 1, endy, Tile::from(TileType::Wall));
         grid.set_empty_tile(x, endy, Tile::from(TileType::Wall));
         grid.set_empty_tile(x + 1, endy, Tile::from(TileType::Wall));
-
-	// Have a door at the start?
-	let mut rng = rand::thread_rng();
-        if rng.gen_bool(0.5) {
-	    grid.set_tile(self.start.0, self.start.1, Tile::from(TileType::Door));
-	}
     }
 
     fn tile_horizontal(&self, grid: &mut TileGrid) {
@@ -212,12 +299,6 @@ impl Corridor {
         grid.set_empty_tile(endx, y - 1, Tile::from(TileType::Wall));
         grid.set_empty_tile(endx, y, Tile::from(TileType::Wall));
         grid.set_empty_tile(endx, y + 1, Tile::from(TileType::Wall));
-
-	// Have a door at the start?
-	let mut rng = rand::thread_rng();
-        if rng.gen_bool(0.5) {
-	    grid.set_tile(self.start.0, self.start.1, Tile::from(TileType::Door));
-	}
     }
 }
 
@@ -232,38 +313,79 @@ impl Tileable for Corridor {
     }
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct Level {
     xsize: usize,
     ysize: usize,
     depth: usize,
     rooms: Vec<Room>,
     corridors: Vec<Corridor>,
+    doors: Vec<Point>,
+    #[serde(with = "entity_list")]
     pub entities: Vec<Box<dyn Entity>>,
+    pub grid: TileGrid,
     entrance: Point,
     exit: Point,
+    /// Where the player already stood on items
+    items_seen: Vec<Point>,
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct Dungeon {
     xsize: usize,
     ysize: usize,
     depth: usize,
+    seed: u64,
     pub levels: Vec<Level>,
 }
 
 pub trait Generatable {
-    fn generate(&mut self);
+    /// Generate the content, drawing every random decision from `rng` so the
+    /// same seed always gives the same result.
+    fn generate<R: Rng>(&mut self, rng: &mut R);
+}
+
+/// Check that a dungeon of `depth` levels of `xsize` by `ysize` tiles can be
+/// generated.
+pub fn check_size(xsize: usize, ysize: usize, depth: usize) -> Result<(), String> {
+    if !(MIN_LEVEL_XSIZE..=MAX_LEVEL_SIZE).contains(&xsize) {
+        return Err(format!(
+            "The width must be between {} and {}",
+            MIN_LEVEL_XSIZE, MAX_LEVEL_SIZE
+        ));
+    }
+    if !(MIN_LEVEL_YSIZE..=MAX_LEVEL_SIZE).contains(&ysize) {
+        return Err(format!(
+            "The height must be between {} and {}",
+            MIN_LEVEL_YSIZE, MAX_LEVEL_SIZE
+        ));
+    }
+    if !(1..=MAX_DEPTH).contains(&depth) {
+        return Err(format!("The depth must be between 1 and {}", MAX_DEPTH));
+    }
+    Ok(())
 }
 
 impl Dungeon {
-    pub fn new(xsize: usize, ysize: usize, depth: usize) -> Dungeon {
+    pub fn new(xsize: usize, ysize: usize, depth: usize, seed: u64) -> Dungeon {
         Dungeon {
             xsize,
             ysize,
             depth,
+            seed,
             levels: vec![],
         }
     }
 
+    /// Random generator for this dungeon's seed, to be passed to `generate`.
+    pub fn rng(&self) -> DungeonRng {
+        DungeonRng::seed_from_u64(self.seed)
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
     pub fn xsize(&self) -> usize {
         self.xsize
     }
@@ -278,15 +400,15 @@ impl Dungeon {
 }
 
 impl Generatable for Dungeon {
-    fn generate(&mut self) {
+    fn generate<R: Rng>(&mut self, rng: &mut R) {
         let mut level = Level::new(self.xsize, self.ysize, 1, None);
-        level.generate();
+        level.generate(rng);
         let mut next_entrance = level.exit();
         self.levels.push(level);
 
         for d in 1..self.depth {
             level = Level::new(self.xsize, self.ysize, d + 1, Some(next_entrance));
-            level.generate();
+            level.generate(rng);
             next_entrance = level.exit();
             self.levels.push(level);
         }
@@ -303,13 +425,16 @@ impl Level {
             ysize,
             rooms: vec![],
             corridors: vec![],
+            doors: vec![],
             entities: vec![],
+            grid: TileGrid::new(xsize, ysize),
             entrance: match start {
                 Some(st) => st,
                 None => (0, 0),
             },
             exit: (0, 0),
             depth,
+            items_seen: vec![],
         }
     }
 
@@ -324,6 +449,10 @@ impl Level {
             corridor.tile(&mut grid)?;
         }
 
+        for door in &self.doors {
+            grid.set_tile(door.0, door.1, Tile::from(TileType::Door));
+        }
+
         grid.set_tile(
             self.entrance.0,
             self.entrance.1,
@@ -341,14 +470,26 @@ impl Level {
         (0, 0)
     }
 
-    // pub fn entrance(&self) -> Point {
-    //     self.entrance
-    // }
+    pub fn entrance(&self) -> Point {
+        self.entrance
+    }
 
     pub fn exit(&self) -> Point {
         self.exit
     }
 
+    /// Remember the player stood on the items lying at `location`.
+    pub fn see_items(&mut self, location: Point) {
+        if !self.items_seen.contains(&location) {
+            self.items_seen.push(location);
+        }
+    }
+
+    /// Whether the player already stood on the items lying at `location`.
+    pub fn items_seen_at(&self, location: Point) -> bool {
+        self.items_seen.contains(&location)
+    }
+
     fn overlaps(&self, start: Point, width: usize, height: usize, padding: usize) -> bool {
         for room in &self.rooms {
             if room.start.0 < start.0 + width + padding
@@ -363,11 +504,9 @@ impl Level {
         false
     }
 
-    fn random_room(&self) -> Result<Room, String> {
-        // TODO: Detect when not enough space is left to allocate a room.
-        let mut rng = rand::thread_rng();
-        let room_width = rng.gen_range(4, 12);
-        let room_height = rng.gen_range(4, 12);
+    fn random_room<R: Rng>(&self, rng: &mut R) -> Result<Room, String> {
+        let room_width = rng.gen_range(MIN_ROOM_SIZE, MAX_ROOM_SIZE + 1);
+        let room_height = rng.gen_range(MIN_ROOM_SIZE, MAX_ROOM_SIZE + 1);
 
         // TODO: Find a way to write a lambda to generate the start point.
         let mut start: Point = (
@@ -375,7 +514,12 @@ impl Level {
             rng.gen_range(0, self.ysize - room_height),
         );
 
-        while self.overlaps(start, room_width, room_height, 2) {
+        let mut attempts = 0;
+        while self.overlaps(start, room_width, room_height, ROOM_PADDING) {
+            attempts += 1;
+            if attempts == ROOM_ATTEMPTS {
+                return Err(String::from("No space left for a room"));
+            }
             start = (
                 rng.gen_range(0, self.xsize - room_width),
                 rng.gen_range(0, self.ysize - room_height),
@@ -385,8 +529,7 @@ impl Level {
         Ok(Room::new(start, room_width, room_height))
     }
 
-    fn centered_room(&self, center: Point) -> Room {
-        let mut rng = rand::thread_rng();
+    fn centered_room<R: Rng>(&self, center: Point, rng: &mut R) -> Room {
         let room_width: usize =
             rng.gen_range(3, min(min(12, (self.xsize - center.0) * 2), center.0 * 2));
         let room_height: usize =
@@ -399,20 +542,33 @@ impl Level {
 
         Room::new(start, room_width, room_height)
     }
+
+    /// Pick a random spot inside one of the rooms to put something on.
+    fn spawn_point<R: Rng>(&self, rng: &mut R) -> Point {
+        let room = &self.rooms[rng.gen_range(0, self.rooms.len())];
+        (
+            room.start.0 + rng.gen_range(0, room.width - 1) + 1,
+            room.start.1 + rng.gen_range(0, room.height - 1) + 1,
+        )
+    }
 }
 
 impl Generatable for Level {
-    fn generate(&mut self) {
-        let mut rng = rand::thread_rng();
-        let room_number = rng.gen_range(3, 5);
+    fn generate<R: Rng>(&mut self, rng: &mut R) {
+        // Bigger levels get more rooms.
+        let room_number = rng.gen_range(3, 3 + max(2, self.xsize * self.ysize / 960));
 
         if self.entrance != (0, 0) {
-            self.rooms.push(self.centered_room(self.entrance));
+            self.rooms.push(self.centered_room(self.entrance, rng));
         }
 
         // Generate rooms
         for _ in self.rooms.len()..room_number {
-            self.rooms.push(self.random_room().unwrap());
+            match self.random_room(rng) {
+                Ok(room) => self.rooms.push(room),
+                // The level is full, make do with the rooms we have.
+                Err(_) => break,
+            }
         }
 
         // Generate corridors
@@ -424,7 +580,7 @@ impl Generatable for Level {
                 &self.rooms[i + 1]
             };
 
-            match Corridor::link(room.center, next_room.center) {
+            match Corridor::link(room.center, next_room.center, rng) {
                 Ok(mut cor) => self.corridors.append(&mut cor),
                 Err(e) => println!("{}", e),
             };
@@ -436,27 +592,37 @@ impl Generatable for Level {
         }
         self.exit = self.rooms.last().unwrap().center;
 
+        // Have a door where some corridors go through a room's wall
+        let grid = self.to_tilegrid().unwrap();
+        for corridor in &self.corridors {
+            for point in corridor.points() {
+                let doorway = self.rooms.iter().any(|room| room.in_wall(point))
+                    && corridor.walled_at(&grid, point)
+                    && !self.doors.contains(&point);
+                if doorway && rng.gen_bool(0.5) {
+                    self.doors.push(point);
+                }
+            }
+        }
+
+        self.grid = self.to_tilegrid().unwrap();
+
         // Populate the level
+        let spawn_table = SpawnTable::new(self.depth);
         let num_enemies: usize = (self.rooms.len() as f32 * self.depth as f32 * 0.5) as usize;
         for _ in 0..num_enemies {
-            // Pick a room
-            let mut rng = rand::thread_rng();
-            let room = &self.rooms[rng.gen_range(0, self.rooms.len() - 1)];
-
-            // Create the enemy
-            let enemy_coords = (
-                room.start.0 + rng.gen_range(0, room.width - 1) + 1,
-                room.start.1 + rng.gen_range(0, room.height - 1) + 1,
-            );
-            self.entities.push(Box::<Character>::new(Enemy::new(
-                String::from("snake"),
-                2 * self.depth as i32,
-                (2.0 * self.depth as f32 * 0.6).round() as i32,
-                (20.0 * self.depth as f32 * 0.2).max(80.0).round() as i32,
-                0,
-                enemy_coords,
-                "s",
-            )));
+            let enemy_coords = self.spawn_point(rng);
+            if let Some(monster) = spawn_table.pick(rng) {
+                self.entities.push(Box::new(monster.create(enemy_coords)));
+            }
+        }
+
+        // Leave some loot around
+        let num_items = self.rooms.len() / 2 + 1;
+        for _ in 0..num_items {
+            let item_coords = self.spawn_point(rng);
+            self.entities
+                .push(Box::new(FloorItem::new(Item::random(rng), item_coords)));
         }
     }
 }
@@ -465,6 +631,131 @@ impl Generatable for Level {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_distance_counts_diagonal_steps() {
+        assert_eq!(distance((2, 3), (2, 3)), 0);
+        assert_eq!(distance((0, 0), (3, 4)), 4);
+        assert_eq!(distance((3, 4), (0, 0)), 4);
+        assert_eq!(distance((1, 1), (2, 2)), 1);
+    }
+
+    #[test]
+    fn test_manhattan_distance_counts_orthogonal_steps() {
+        assert_eq!(manhattan_distance((0, 0), (3, 4)), 7);
+        assert_eq!(manhattan_distance((3, 4), (0, 0)), 7);
+    }
+
+    #[test]
+    fn test_direction_heads_towards_the_target() {
+        assert_eq!(direction((2, 2), (5, 2)), RIGHT);
+        assert_eq!(direction((2, 2), (0, 0)), UP_LEFT);
+        assert_eq!(direction((2, 2), (3, 9)), DOWN_RIGHT);
+        assert_eq!(direction((2, 2), (2, 2)), (0, 0));
+    }
+
+    #[test]
+    fn test_spawn_points_are_inside_the_rooms() {
+        let mut level = Level::new(80, 24, 1, None);
+        level.rooms.push(Room::new((2, 2), 5, 5));
+        let mut rng = DungeonRng::seed_from_u64(0);
+        for _ in 0..100 {
+            let (x, y) = level.spawn_point(&mut rng);
+            assert!((3..7).contains(&x) && (3..7).contains(&y));
+        }
+
+        // Every room gets its turn, the last one included.
+        level.rooms.push(Room::new((20, 2), 5, 5));
+        assert!((0..100).any(|_| level.spawn_point(&mut rng).0 > 20));
+    }
+
+    #[test]
+    fn test_generated_level_keeps_its_grid() {
+        let mut level = Level::new(80, 24, 1, None);
+        level.generate(&mut DungeonRng::seed_from_u64(0));
+
+        let entrance = level.entrance();
+        let exit = level.exit();
+        assert!(matches!(
+            level.grid.tile_at(entrance.0, entrance.1).get_type(),
+            TileType::StairsUp
+        ));
+        assert!(matches!(
+            level.grid.tile_at(exit.0, exit.1).get_type(),
+            TileType::StairsDown
+        ));
+    }
+
+    #[test]
+    fn test_doors_are_in_room_walls() {
+        for seed in 0..20 {
+            let mut level = Level::new(80, 24, 1, None);
+            level.generate(&mut DungeonRng::seed_from_u64(seed));
+
+            for door in &level.doors {
+                assert!(level.rooms.iter().any(|room| room.in_wall(*door)));
+                let (x, y) = *door;
+                let walls = [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+                    .iter()
+                    .filter(|p| *level.grid.tile_at(p.0, p.1).get_type() == TileType::Wall)
+                    .count();
+                assert_eq!(walls, 2, "door at {:?} isn't in a doorway", door);
+            }
+        }
+    }
+
+    fn generate_dungeon(seed: u64) -> Dungeon {
+        let mut dungeon = Dungeon::new(80, 24, 5, seed);
+        let mut rng = dungeon.rng();
+        dungeon.generate(&mut rng);
+        dungeon
+    }
+
+    #[test]
+    fn test_same_seed_generates_the_same_dungeon() {
+        let first = generate_dungeon(42);
+        let second = generate_dungeon(42);
+
+        assert_eq!(first.levels.len(), 5);
+        for (a, b) in first.levels.iter().zip(second.levels.iter()) {
+            assert!(a.grid == b.grid);
+            assert_eq!(a.entrance(), b.entrance());
+            assert_eq!(a.exit(), b.exit());
+            let a_entities: Vec<Point> = a.entities.iter().map(|e| *e.location()).collect();
+            let b_entities: Vec<Point> = b.entities.iter().map(|e| *e.location()).collect();
+            assert_eq!(a_entities, b_entities);
+        }
+    }
+
+    #[test]
+    fn test_size_checks_match_what_generation_handles() {
+        assert!(check_size(80, 24, 5).is_ok());
+        assert!(check_size(MIN_LEVEL_XSIZE - 1, 24, 5).is_err());
+        assert!(check_size(80, MIN_LEVEL_YSIZE - 1, 5).is_err());
+        assert!(check_size(MAX_LEVEL_SIZE + 1, 24, 5).is_err());
+        assert!(check_size(80, 24, 0).is_err());
+        assert!(check_size(80, 24, MAX_DEPTH + 1).is_err());
+
+        // The smallest allowed dungeons can always be generated.
+        for seed in 0..200 {
+            let mut dungeon = Dungeon::new(MIN_LEVEL_XSIZE, MIN_LEVEL_YSIZE, 3, seed);
+            let mut rng = dungeon.rng();
+            dungeon.generate(&mut rng);
+            assert_eq!(dungeon.levels.len(), 3);
+        }
+    }
+
+    #[test]
+    fn test_different_seeds_generate_different_dungeons() {
+        let first = generate_dungeon(1);
+        let second = generate_dungeon(2);
+
+        assert!(first
+            .levels
+            .iter()
+            .zip(second.levels.iter())
+            .any(|(a, b)| a.grid != b.grid));
+    }
+
     #[test]
     fn test_make_corridor_detects_horizontal() {
         let start = (0, 0);
@@ -501,7 +792,8 @@ mod tests {
 
     #[test]
     fn test_link_corridors_returns_a_vec_of_corridors() {
-        let cor = Corridor::link((0, 0), (5, 5)).unwrap();
+        let mut rng = DungeonRng::seed_from_u64(0);
+        let cor = Corridor::link((0, 0), (5, 5), &mut rng).unwrap();
 
         let exp_horz = vec![
             Corridor::new((0, 0), 5, CorridorType::Horizontal),
@@ -520,7 +812,8 @@ mod tests {
 
     #[test]
     fn test_link_corridors_returns_a_vec_of_corridors_on_reversed_diagonal_points() {
-        let cor = Corridor::link((5, 5), (0, 0)).unwrap();
+        let mut rng = DungeonRng::seed_from_u64(0);
+        let cor = Corridor::link((5, 5), (0, 0), &mut rng).unwrap();
 
         let exp_horz = vec![
             Corridor::new((0, 5), 5, CorridorType::Horizontal),
@@ -539,7 +832,8 @@ mod tests {
 
     #[test]
     fn test_link_corridors_returns_a_vec_of_corridors_on_reversed_vertical_points() {
-        let cor = Corridor::link((0, 5), (5, 0)).unwrap();
+        let mut rng = DungeonRng::seed_from_u64(0);
+        let cor = Corridor::link((0, 5), (5, 0), &mut rng).unwrap();
 
         let exp_horz = vec![
             Corridor::new((0, 5), 5, CorridorType::Horizontal),
@@ -558,7 +852,8 @@ mod tests {
 
     #[test]
     fn test_link_corridors_returns_a_vec_of_corridors_on_reversed_horizontal_points() {
-        let cor = Corridor::link((5, 0), (0, 5)).unwrap();
+        let mut rng = DungeonRng::seed_from_u64(0);
+        let cor = Corridor::link((5, 0), (0, 5), &mut rng).unwrap();
 
         let exp_horz = vec![
             Corridor::new((0, 0), 5, CorridorType::Horizontal),
@@ -577,7 +872,8 @@ mod tests {
 
     #[test]
     fn test_link_corridors_with_horizontal_aligned_points_returns_one_corridor() {
-        let cor = Corridor::link((0, 0), (5, 0)).unwrap();
+        let mut rng = DungeonRng::seed_from_u64(0);
+        let cor = Corridor::link((0, 0), (5, 0), &mut rng).unwrap();
 
         assert_eq!(cor.len(), 1);
         assert_eq!(cor[0], Corridor::new((0, 0), 5, CorridorType::Horizontal));
@@ -585,7 +881,8 @@ mod tests {
 
     #[test]
     fn test_link_corridors_with_vertical_aligned_points_returns_one_corridor() {
-        let cor = Corridor::link((0, 0), (0, 5)).unwrap();
+        let mut rng = DungeonRng::seed_from_u64(0);
+        let cor = Corridor::link((0, 0), (0, 5), &mut rng).unwrap();
 
         assert_eq!(cor.len(), 1);
         assert_eq!(cor[0], Corridor::new((0, 0), 5, CorridorType::Vertical));